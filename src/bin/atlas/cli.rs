@@ -10,10 +10,12 @@ use std::path::PathBuf;
 use clap::{crate_authors, Parser};
 
 // Import our various data structures & enums
+use crate::cmds::cache::CacheOpts;
 use crate::cmds::credits::CredOpts;
 use crate::cmds::ip::IpOpts;
 use crate::cmds::keys::KeyOpts;
 use crate::cmds::measurements::MeasurementOpts;
+use crate::cmds::parse::ParseOpts;
 use crate::cmds::probes::ProbeOpts;
 use crate::proto::{DnsOpts, HttpOpts, NtpOpts, PingOpts, TlsOpts, TrrOpts};
 
@@ -33,6 +35,9 @@ pub(crate) struct Opts {
     /// debug mode
     #[clap(short = 'D', long = "debug")]
     pub(crate) debug: bool,
+    /// Only use cached answers, never the network
+    #[clap(short = 'o', long)]
+    pub(crate) offline: bool,
     /// Verbose mode
     #[clap(short = 'v', long)]
     pub(crate) verbose: bool,
@@ -78,4 +83,10 @@ pub(crate) enum SubCommand {
 
     /// Displays the default probe IPs
     Ip(IpOpts),
+
+    /// Manage the on-disk answer cache
+    Cache(CacheOpts),
+
+    /// Read result dump files (NDJSON, possibly compressed)
+    Parse(ParseOpts),
 }
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use atlas_rs::cache::Cache;
+
+use crate::config::default_cache_dir;
+use crate::Context;
+
+/// Cache options
+///
+#[derive(Parser)]
+pub(crate) struct CacheOpts {
+    /// Subcommands
+    #[clap(subcommand)]
+    pub(crate) subcmd: CacheSubCommand,
+}
+
+/// Cache subcommands
+///
+#[derive(Parser)]
+pub(crate) enum CacheSubCommand {
+    /// Remove every entry
+    Clear,
+    /// Display the number of entries and their size
+    Stats,
+}
+
+pub(crate) fn cmd_cache(ctx: &Context, opts: CacheOpts) {
+    let dir: PathBuf = match ctx.cfg.cache.as_ref().and_then(|c| c.dir.clone()) {
+        Some(dir) => dir,
+        None => default_cache_dir().unwrap(),
+    };
+    let cache = Cache::new(&dir);
+
+    match opts.subcmd {
+        CacheSubCommand::Clear => match cache.clear() {
+            Ok(n) => println!("{} entries removed from {:?}", n, dir),
+            Err(e) => eprintln!("Error: {}", e),
+        },
+        CacheSubCommand::Stats => match cache.stats() {
+            Ok(st) => println!(
+                "{:?}: {} entries ({} expired), {} bytes",
+                dir, st.entries, st.expired, st.bytes
+            ),
+            Err(e) => eprintln!("Error: {}", e),
+        },
+    }
+}
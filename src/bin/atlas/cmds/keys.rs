@@ -1,6 +1,7 @@
 use clap::Parser;
 
 use atlas_rs::core::keys::*;
+use atlas_rs::ids::KeyUuid;
 
 use crate::cmds::common::ListOpts;
 use crate::Context;
@@ -39,9 +40,13 @@ pub(crate) struct KInfoOpts {
 pub(crate) fn cmd_keys(ctx: &Context, opts: KeyOpts) {
     match opts.subcmd {
         KeySubCommand::Info(opts) => {
-            let uuid = opts.uuid.unwrap_or_else(|| ctx.cfg.api_key.clone());
+            let uuid: KeyUuid = opts
+                .uuid
+                .unwrap_or_else(|| ctx.cfg.api_key.clone())
+                .parse()
+                .unwrap();
 
-            let k: Key = ctx.c.keys().get(uuid.as_str()).unwrap();
+            let k: Key = ctx.c.keys().get(uuid.clone()).unwrap();
             println!("Key {} is:\n{:?}", uuid, k);
         }
         KeySubCommand::List(_opts) => (),
@@ -1,8 +1,10 @@
-//use atlas_rs::core::measurements::*;
-
 use clap::Parser;
 
+use atlas_rs::core::measurements::*;
+use atlas_rs::ids::MeasurementId;
+
 use crate::cmds::common::{InfoOpts, ListOpts};
+use crate::Context;
 
 /// Measurements options
 ///
@@ -22,4 +24,116 @@ pub(crate) struct MeasurementOpts {
 pub(crate) enum MeasurementSubCommand {
     Info(InfoOpts),
     List(ListOpts),
+    /// Stop one or more measurements
+    Stop(StopOpts),
+}
+
+/// Stop options, either a list of IDs or a selection of our own measurements.  A selection
+/// is only shown unless `--yes` is given.
+///
+#[derive(Parser)]
+pub(crate) struct StopOpts {
+    /// Only show what would be stopped
+    #[clap(short = 'n', long)]
+    pub(crate) dry_run: bool,
+    /// Really stop the selected measurements
+    #[clap(short, long)]
+    pub(crate) yes: bool,
+    /// Measurements with this tag
+    #[clap(short, long)]
+    pub(crate) tag: Option<String>,
+    /// Measurements whose description matches this regex
+    #[clap(short, long)]
+    pub(crate) description: Option<String>,
+    /// Measurements towards this target
+    #[clap(short = 'T', long)]
+    pub(crate) target: Option<String>,
+    /// Measurements of this group
+    #[clap(short, long)]
+    pub(crate) group: Option<u32>,
+    /// Measurement IDs
+    pub(crate) ids: Vec<u32>,
+}
+
+pub(crate) fn cmd_measurements(ctx: &Context, opts: MeasurementOpts) {
+    match opts.subcmd {
+        MeasurementSubCommand::Info(opts) => {
+            let id = opts.id.unwrap();
+
+            let m: Measurement = ctx.c.measurement().get(id).unwrap();
+            println!("Measurement {} is:\n{:?}", id, m);
+        }
+        MeasurementSubCommand::List(_opts) => (),
+        MeasurementSubCommand::Stop(opts) => cmd_stop(ctx, opts),
+    }
+}
+
+fn cmd_stop(ctx: &Context, opts: StopOpts) {
+    // Explicit list of measurements
+    if !opts.ids.is_empty() {
+        for id in opts.ids {
+            if opts.dry_run {
+                println!("Would stop {}", id);
+                continue;
+            }
+            match ctx.c.measurement().delete(id) {
+                Ok(()) => println!("Stopped {}", id),
+                Err(e) => println!("Err {}: {:?}", id, e),
+            }
+        }
+        return;
+    }
+
+    let mut sel = MeasurementSelector::new();
+    if let Some(t) = &opts.tag {
+        sel = sel.tag(t);
+    }
+    if let Some(d) = &opts.description {
+        sel = match sel.description(d) {
+            Ok(sel) => sel,
+            Err(e) => {
+                eprintln!("Error: {}", e.error.detail);
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(t) = &opts.target {
+        sel = sel.target(t);
+    }
+    if let Some(g) = opts.group {
+        sel = sel.group(MeasurementId(g));
+    }
+
+    let dry_run = opts.dry_run || !opts.yes;
+    let r = match ctx.c.stop_measurements(&sel, dry_run) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e.error.detail);
+            std::process::exit(1);
+        }
+    };
+    for i in &r.items {
+        println!(
+            "{:>10} {:>8} {:<30} {}",
+            i.id,
+            i.daily_cost.map(|c| c.to_string()).unwrap_or_default(),
+            i.target.as_deref().unwrap_or(""),
+            i.description.as_deref().unwrap_or("")
+        );
+    }
+    for (id, res) in &r.stopped {
+        if let Err(e) = res {
+            println!("Err {}: {:?}", id, e);
+        }
+    }
+    let verb = if dry_run { "Would stop" } else { "Stopped" };
+    println!(
+        "{} {} measurements, saving about {} credits/day",
+        verb,
+        r.items.len() - r.stopped.iter().filter(|(_, e)| e.is_err()).count(),
+        r.daily_credits
+    );
+    if dry_run && !opts.dry_run {
+        println!("Use --yes to stop them");
+    }
 }
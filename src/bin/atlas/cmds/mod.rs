@@ -1,6 +1,8 @@
+pub mod cache;
 pub mod common;
 pub mod credits;
 pub mod ip;
 pub mod keys;
 pub mod measurements;
+pub mod parse;
 pub mod probes;
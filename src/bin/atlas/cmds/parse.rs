@@ -0,0 +1,94 @@
+use clap::Parser;
+
+use atlas_rs::kinds::MeasurementType;
+use atlas_rs::reader::{ResultFilter, ResultReader};
+use atlas_rs::timestamp::Timestamp;
+
+/// Options for parsing result dumps
+///
+#[derive(Parser)]
+pub(crate) struct ParseOpts {
+    /// Only results of this measurement (can be repeated)
+    #[clap(short, long)]
+    pub(crate) msm: Vec<u32>,
+    /// Only results from this probe (can be repeated)
+    #[clap(short, long)]
+    pub(crate) probe: Vec<u32>,
+    /// Only results of this type
+    #[clap(short = 't', long = "type")]
+    pub(crate) mtype: Option<MeasurementType>,
+    /// Only results at or after this time (POSIX time or ISO 8601)
+    #[clap(short, long, parse(try_from_str = parse_time))]
+    pub(crate) since: Option<Timestamp>,
+    /// Only results before this time (POSIX time or ISO 8601)
+    #[clap(short, long, parse(try_from_str = parse_time))]
+    pub(crate) until: Option<Timestamp>,
+    /// Only display the number of matching results
+    #[clap(short = 'n', long)]
+    pub(crate) count: bool,
+    /// Dump files, possibly compressed
+    #[clap(required = true)]
+    pub(crate) files: Vec<String>,
+}
+
+/// Accept both POSIX times and ISO 8601 dates
+///
+fn parse_time(s: &str) -> Result<Timestamp, String> {
+    s.parse()
+        .map_err(|e: atlas_rs::errors::APIError| e.error.detail)
+}
+
+impl From<&ParseOpts> for ResultFilter {
+    fn from(opts: &ParseOpts) -> Self {
+        let mut f = ResultFilter::new();
+        for id in &opts.msm {
+            f = f.msm(*id);
+        }
+        for id in &opts.probe {
+            f = f.probe(*id);
+        }
+        if let Some(t) = &opts.mtype {
+            f = f.mtype(t.clone());
+        }
+        if let Some(ts) = opts.since {
+            f = f.since(ts);
+        }
+        if let Some(ts) = opts.until {
+            f = f.until(ts);
+        }
+        f
+    }
+}
+
+/// Read dump files locally, no API call is made.
+///
+pub(crate) fn cmd_parse(opts: ParseOpts) {
+    let filter = ResultFilter::from(&opts);
+    let mut total = 0;
+
+    for fname in &opts.files {
+        let rd = match ResultReader::from_file(fname) {
+            Ok(rd) => rd.filter_by(filter.clone()),
+            Err(e) => {
+                eprintln!("{}: {}", fname, e.error.detail);
+                continue;
+            }
+        };
+
+        for r in rd {
+            match r {
+                Ok(r) => {
+                    total += 1;
+                    if !opts.count {
+                        println!("{}", r);
+                    }
+                }
+                Err(e) => eprintln!("{}: {}", fname, e.error.detail),
+            }
+        }
+    }
+
+    if opts.count {
+        println!("{}", total);
+    }
+}
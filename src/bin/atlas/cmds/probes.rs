@@ -1,6 +1,8 @@
 use clap::Parser;
 
+use atlas_rs::core::measurements::{Measurement, MeasurementFilter};
 use atlas_rs::core::probes::*;
+use atlas_rs::kinds::{MeasurementState, MeasurementType};
 
 use crate::cmds::common::{InfoOpts, ListOpts};
 use crate::Context;
@@ -23,6 +25,32 @@ pub(crate) struct ProbeOpts {
 pub(crate) enum ProbeSubCommand {
     Info(InfoOpts),
     List(ListOpts),
+    /// Measurements the probe is participating in
+    #[clap(visible_alias = "msm")]
+    Measurements(MsmOpts),
+}
+
+/// Probe measurements options
+///
+#[derive(Parser)]
+pub(crate) struct MsmOpts {
+    /// Print debug info
+    #[clap(short)]
+    pub(crate) debug: bool,
+    /// Measurement type (ping, traceroute, dns, sslcert, http, ntp)
+    #[clap(short = 't', long = "type")]
+    pub(crate) mtype: Option<MeasurementType>,
+    /// Measurement status (ongoing, stopped, etc.)
+    #[clap(short, long)]
+    pub(crate) status: Option<MeasurementState>,
+    /// Inet family (4 or 6)
+    #[clap(short, long)]
+    pub(crate) af: Option<u32>,
+    /// Only public (true) or private (false) measurements
+    #[clap(short = 'p', long)]
+    pub(crate) is_public: Option<bool>,
+    /// Probe ID
+    pub(crate) id: Option<u32>,
 }
 
 pub(crate) fn cmd_probes(ctx: &Context, opts: ProbeOpts) {
@@ -34,5 +62,51 @@ pub(crate) fn cmd_probes(ctx: &Context, opts: ProbeOpts) {
             println!("Probe {} is:\n{:?}", pn, p);
         }
         ProbeSubCommand::List(_opts) => unimplemented!(),
+        ProbeSubCommand::Measurements(opts) => {
+            let pn = opts.id.unwrap_or_else(|| ctx.cfg.default_probe.unwrap());
+
+            let mut filter = MeasurementFilter::new();
+            if let Some(t) = opts.mtype {
+                filter = filter.mtype(t);
+            }
+            if let Some(s) = opts.status {
+                filter = filter.status(s);
+            }
+            if let Some(af) = opts.af {
+                filter = filter.af(af);
+            }
+            if let Some(p) = opts.is_public {
+                filter = filter.is_public(p);
+            }
+
+            let list = ctx.c.probe().with(filter).measurements(pn).unwrap();
+
+            println!("Measurements for probe {}:", pn);
+            for m in list {
+                match m {
+                    Ok(m) => print_measurement(&m),
+                    Err(e) => {
+                        println!("Err: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        }
     }
 }
+
+/// One line per measurement
+///
+fn print_measurement(m: &Measurement) {
+    println!(
+        "{:>10} {:<10} {:<10} {:<23} {}",
+        m.id,
+        m.mtype.as_ref().map(|t| t.to_string()).unwrap_or_default(),
+        m.status
+            .as_ref()
+            .map(|s| s.name.to_string())
+            .unwrap_or_default(),
+        m.start_time.map(|t| t.to_string()).unwrap_or_default(),
+        m.description.as_deref().unwrap_or("")
+    );
+}
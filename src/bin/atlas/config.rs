@@ -16,8 +16,33 @@
 //! type = "area"
 //! value = "WW"
 //! tags = "+ipv4"
+//!
+//! [http]
+//!
+//! connect_timeout = 10
+//! timeout = 300
+//! proxy = "socks5://localhost:1080"
+//! ca_file = "/etc/ssl/corporate-ca.pem"
+//! user_agent = "my-script/1.0"
+//!
+//! [cache]
+//!
+//! enabled = true
+//! dir = "/var/tmp/atlas"
+//! offline = false
+//!
+//! [cache.ttl]
+//!
+//! probes = 3600
+//! anchors = 86400
 //! ```
 //!
+//! The `[http]` section is optional, timeouts are in seconds (`0` for no limit on the whole
+//! request).
+//!
+//! The `[cache]` section is optional, TTLs are in seconds and the cache directory defaults to
+//! `$HOME/.cache/atlas-rs/` (`%LOCALAPPDATA%\atlas-rs\cache\` on Windows).
+//!
 //! On Unix systems (FreeBSD, macOS, Linux, etc.) the default configuration
 //! directory is `$HOME/.config/atlas-rs/` whereas on Windows, it is located
 //! in `%LOCALAPPDATA%\atlas-rs\`.
@@ -58,9 +83,11 @@
 //! [TOML]: https://crates.io/crates/toml
 
 // Standard library
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
 // External crates
 use anyhow::Result;
@@ -69,6 +96,9 @@ use clap::crate_name;
 use home::home_dir;
 use serde::Deserialize;
 
+use atlas_rs::cache::CacheConfig;
+use atlas_rs::client::ClientBuilder;
+
 /// Default configuration filename
 const CONFIG: &str = "config.toml";
 
@@ -76,6 +106,10 @@ const CONFIG: &str = "config.toml";
 #[cfg(unix)]
 const BASEDIR: &str = ".config";
 
+/// Use the standard location `$HOME/.cache`
+#[cfg(unix)]
+const CACHEDIR: &str = ".cache";
+
 /// Default set of probes to be used for queries
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ProbeSet {
@@ -100,6 +134,80 @@ pub struct Measurements {
     pub bill_to: String,
 }
 
+/// HTTP settings, timeouts in seconds
+///
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Http {
+    /// Time to establish the connection
+    pub connect_timeout: Option<u64>,
+    /// Time for the whole request, 0 means no limit
+    pub timeout: Option<u64>,
+    /// HTTP, HTTPS or SOCKS5 proxy URL
+    pub proxy: Option<String>,
+    /// PEM file with extra CA certificates
+    pub ca_file: Option<PathBuf>,
+    /// Added to the `User-Agent` header
+    pub user_agent: Option<String>,
+}
+
+impl Http {
+    /// Apply these settings to the client being built
+    ///
+    pub fn apply(&self, mut cb: ClientBuilder) -> ClientBuilder {
+        if let Some(t) = self.connect_timeout {
+            cb = cb.connect_timeout(Duration::from_secs(t));
+        }
+        if let Some(t) = self.timeout {
+            cb = cb.timeout(Duration::from_secs(t));
+        }
+        if let Some(p) = &self.proxy {
+            cb = cb.proxy(p);
+        }
+        if let Some(f) = &self.ca_file {
+            cb = cb.ca_file(f);
+        }
+        if let Some(ua) = &self.user_agent {
+            cb = cb.user_agent(ua);
+        }
+        cb
+    }
+}
+
+/// On-disk cache of API answers
+///
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Cache {
+    /// Use the cache at all
+    pub enabled: Option<bool>,
+    /// Where to store entries
+    pub dir: Option<PathBuf>,
+    /// Never use the network
+    pub offline: Option<bool>,
+    /// TTL in seconds per context (`probes`, `anchors`, etc.)
+    pub ttl: Option<HashMap<String, u64>>,
+}
+
+impl Cache {
+    /// Turn this into the library configuration, `None` if the cache is disabled
+    ///
+    pub fn to_config(&self) -> Option<CacheConfig> {
+        if !self.enabled.unwrap_or(false) {
+            return None;
+        }
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => default_cache_dir().ok()?,
+        };
+        let mut cfg = CacheConfig::new(dir).offline(self.offline.unwrap_or(false));
+        if let Some(ttl) = &self.ttl {
+            for (ctx, secs) in ttl {
+                cfg = cfg.ttl(ctx, Duration::from_secs(*secs));
+            }
+        }
+        Some(cfg)
+    }
+}
+
 /// `Config` struct with one mandatory argument and optional ones.
 ///
 /// Most API calls need an API key.
@@ -111,9 +219,15 @@ pub struct Config {
     /// Default probe ID
     pub default_probe: Option<u32>,
     /// Default set of probes
+    #[allow(dead_code)]
     pub probe_set: Option<ProbeSet>,
     /// Stuff about billing to a specific account
+    #[allow(dead_code)]
     pub measurements: Option<Measurements>,
+    /// HTTP settings
+    pub http: Option<Http>,
+    /// Response cache
+    pub cache: Option<Cache>,
 }
 
 /// Here are the "reasonable" defaults.
@@ -131,6 +245,8 @@ impl Default for Config {
                 tags: Some("".to_string()),
             }),
             measurements: None,
+            http: None,
+            cache: None,
         }
     }
 }
@@ -204,6 +320,38 @@ pub fn default_file() -> Result<PathBuf> {
     Ok(def)
 }
 
+/// Returns the default cache directory, `$HOME/.cache/atlas-rs` on Unix systems.
+///
+#[cfg(unix)]
+pub fn default_cache_dir() -> Result<PathBuf> {
+    let homedir = home_dir().unwrap();
+
+    let def: PathBuf = [
+        homedir,
+        PathBuf::from(CACHEDIR),
+        PathBuf::from(crate_name!()),
+    ]
+    .iter()
+    .collect();
+    Ok(def)
+}
+
+/// Returns the default cache directory, `%LOCALAPPDATA%\atlas-rs\cache` on Windows.
+///
+#[cfg(windows)]
+pub fn default_cache_dir() -> Result<PathBuf> {
+    let basedir = env::var("LOCALAPPDATA")?;
+
+    let def: PathBuf = [
+        PathBuf::from(basedir),
+        PathBuf::from(crate_name!()),
+        PathBuf::from("cache"),
+    ]
+    .iter()
+    .collect();
+    Ok(def)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,6 +372,40 @@ mod tests {
         assert_eq!(Some(666), c.default_probe);
     }
 
+    #[test]
+    fn test_load_cache() {
+        let c = Config::load(&PathBuf::from("src/bin/atlas/config.toml")).unwrap();
+        let cache = c.cache.unwrap().to_config().unwrap();
+
+        assert_eq!(PathBuf::from("/var/tmp/atlas-rs"), cache.dir);
+        assert!(!cache.offline);
+        assert_eq!(Some(&Duration::from_secs(600)), cache.ttls.get("probes"));
+        assert_eq!(Some(&Duration::from_secs(86400)), cache.ttls.get("anchors"));
+    }
+
+    #[test]
+    fn test_load_http() {
+        let c = Config::load(&PathBuf::from("src/bin/atlas/config.toml")).unwrap();
+        let http = c.http.unwrap();
+
+        assert_eq!(Some(300), http.timeout);
+        assert_eq!(None, http.proxy);
+        assert!(http
+            .apply(ClientBuilder::new().api_key("FOO"))
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        let c = Cache {
+            dir: Some(PathBuf::from("/tmp")),
+            ..Default::default()
+        };
+
+        assert_eq!(None, c.to_config());
+    }
+
     #[test]
     fn test_load_nok() {
         let c = Config::load(&PathBuf::from("/nonexistent"));
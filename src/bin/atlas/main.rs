@@ -3,21 +3,29 @@
 //! It is a way to both demonstrate the use of the API and a testing tool.
 //!
 
+// Std library
+//
+use std::path::PathBuf;
+
 // External crates
 //
 use anyhow::Result;
 use clap::Parser;
+use log::{warn, LevelFilter};
 
 // API-related ones.
 //
 use atlas_rs::client::{Client, ClientBuilder};
 use cli::{Opts, SubCommand, NAME, VERSION};
-use config::{default_file, Config};
+use config::{default_file, Cache, Config};
 
 // Import all subcommands
+use crate::cmds::cache::cmd_cache;
 use crate::cmds::credits::cmd_credits;
 use crate::cmds::ip::cmd_ip;
 use crate::cmds::keys::cmd_keys;
+use crate::cmds::measurements::cmd_measurements;
+use crate::cmds::parse::cmd_parse;
 use crate::cmds::probes::cmd_probes;
 
 // Link with other modules.
@@ -32,12 +40,12 @@ fn load_config(opts: &Opts) -> Config {
     // Handle configuration loading & defaults
     match &opts.config {
         Some(fname) => Config::load(fname).unwrap_or_else(|e| {
-            println!("No config file, using defaults: {}", e);
+            warn!("No config file, using defaults: {}", e);
             Config::new()
         }),
         None => {
             let cnf = default_file().unwrap();
-            Config::load(&cnf).unwrap_or_default()
+            Config::load(&PathBuf::from(cnf)).unwrap_or_default()
         }
     }
 }
@@ -59,17 +67,46 @@ pub struct Context {
 fn main() -> Result<()> {
     let opts: Opts = Opts::parse();
 
-    if opts.debug {
-        println!("DEBUG MODE");
-    }
+    // Logs go to stderr, `-D` and `-v` raise the level, `RUST_LOG` has the last word
+    let level = if opts.debug {
+        LevelFilter::Debug
+    } else if opts.verbose {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Warn
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
 
     // Handle configuration loading & defaults
     let cfg = load_config(&opts);
 
-    let c = ClientBuilder::new()
-        .api_key(&*cfg.api_key)
-        .verbose(opts.verbose)
-        .build()?;
+    let mut cb = ClientBuilder::new()
+        .api_key(&cfg.api_key)
+        .verbose(opts.verbose);
+
+    // HTTP settings come first as they recreate the transport
+    if let Some(http) = &cfg.http {
+        cb = http.apply(cb);
+    }
+
+    // Cache answers if configured, --offline implies it
+    let cache = cfg.cache.clone().unwrap_or_default();
+    let cache = if opts.offline {
+        Cache {
+            enabled: Some(true),
+            offline: Some(true),
+            ..cache
+        }
+    } else {
+        cache
+    };
+    if let Some(cc) = cache.to_config() {
+        cb = cb.cache(cc);
+    }
+    let c = cb.build()?;
 
     // create the context of every operation
     let ctx = Context { c, cfg };
@@ -79,7 +116,7 @@ fn main() -> Result<()> {
         SubCommand::Probe(opts) => cmd_probes(&ctx, opts),
         SubCommand::Key(opts) => cmd_keys(&ctx, opts),
         SubCommand::Credits(opts) => cmd_credits(&ctx, opts),
-        SubCommand::Measurement(_opts) => (),
+        SubCommand::Measurement(opts) => cmd_measurements(&ctx, opts),
         // protocols-related commands
         SubCommand::Dns(_opts) => (),
         SubCommand::Http(_opts) => (),
@@ -89,6 +126,8 @@ fn main() -> Result<()> {
         SubCommand::Traceroute(_opts) => (),
         // extra utility command
         SubCommand::Ip(opts) => cmd_ip(&ctx, opts),
+        SubCommand::Cache(opts) => cmd_cache(&ctx, opts),
+        SubCommand::Parse(opts) => cmd_parse(opts),
         SubCommand::Version => {
             let v = atlas_rs::version();
 
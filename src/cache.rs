@@ -0,0 +1,534 @@
+//! Module implementing an optional on-disk cache of API answers.
+//!
+//! Probe and anchor metadata rarely change so there is no need to fetch them every time.  When
+//! enabled with `ClientBuilder::cache()`, successful `GET` answers are saved in a directory,
+//! one file per URL (the API key is never part of it), for a duration depending on the context
+//! (`probes`, `anchors`, etc.).  A context with no TTL is not cached, and keys never are as
+//! their ID is the secret itself.  Any change to an object (`PATCH`, `DELETE`, etc.) removes
+//! all the cached answers about it, whatever their options.
+//!
+//! Once an entry is too old, it is revalidated with `If-None-Match`/`If-Modified-Since` if the
+//! API gave us an `ETag` or `Last-Modified` header, so an unchanged object costs a `304`.
+//!
+//! In offline mode, nothing goes to the network and only what is in the cache, however old, is
+//! returned.
+//!
+//! Example:
+//! ```no_run
+//! use std::time::Duration;
+//! use atlas_rs::cache::CacheConfig;
+//! use atlas_rs::client::ClientBuilder;
+//! use atlas_rs::core::probes::Probe;
+//!
+//! let cache = CacheConfig::new("/tmp/atlas-cache").ttl("measurements", Duration::from_secs(60));
+//! let c = ClientBuilder::new().api_key("FOO").cache(cache).build().unwrap();
+//!
+//! // Second call comes from the cache
+//! let p: Probe = c.probe().get(666).unwrap();
+//! let p: Probe = c.probe().get(666).unwrap();
+//! ```
+//!
+
+// Standard library
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Our crates
+use crate::cassette::normalise;
+use crate::errors::APIError;
+use crate::transport::{HttpRequest, HttpResponse, Method, Transport, SECRET_PATHS};
+
+// ---------------------------------------------------------------------------
+
+/// Default TTL for probes
+const PROBES_TTL: Duration = Duration::from_secs(3600);
+
+/// Default TTL for anchors
+const ANCHORS_TTL: Duration = Duration::from_secs(86400);
+
+/// Extension of cache files
+const EXT: &str = "json";
+
+// ---------------------------------------------------------------------------
+
+/// How the cache behaves
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheConfig {
+    /// Where entries are stored
+    pub dir: PathBuf,
+    /// TTL per context (first part of the path like `probes`)
+    pub ttls: HashMap<String, Duration>,
+    /// Only answer from the cache
+    pub offline: bool,
+}
+
+impl CacheConfig {
+    /// Cache in `dir` with the default TTLs: one hour for probes, one day for anchors.
+    ///
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        CacheConfig {
+            dir: dir.as_ref().to_path_buf(),
+            ttls: HashMap::from([
+                ("probes".to_string(), PROBES_TTL),
+                ("anchors".to_string(), ANCHORS_TTL),
+            ]),
+            offline: false,
+        }
+    }
+
+    /// Set the TTL of a context, zero disables caching for it
+    ///
+    pub fn ttl(mut self, ctx: &str, ttl: Duration) -> Self {
+        self.ttls.insert(ctx.to_string(), ttl);
+        self
+    }
+
+    /// Never use the network
+    ///
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// One cached answer
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct Entry {
+    /// URL without the key
+    url: String,
+    /// When it was stored or revalidated
+    stored: u64,
+    /// When it has to be revalidated
+    expires: u64,
+    /// Validators
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Answer
+    body: String,
+}
+
+/// What is in the cache directory
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    /// Number of entries
+    pub entries: usize,
+    /// Of which too old
+    pub expired: usize,
+    /// Total size in bytes
+    pub bytes: u64,
+}
+
+/// Cache directory management
+///
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Use `dir`, created on first write
+    ///
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Cache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Count entries
+    ///
+    pub fn stats(&self) -> Result<CacheStats, APIError> {
+        let now = now();
+        let mut st = CacheStats::default();
+
+        for path in self.files()? {
+            st.entries += 1;
+            st.bytes += fs::metadata(&path)?.len();
+            match read_entry(&path) {
+                Some(e) if e.expires > now => (),
+                _ => st.expired += 1,
+            }
+        }
+        Ok(st)
+    }
+
+    /// Remove all entries, returning how many there were
+    ///
+    pub fn clear(&self) -> Result<usize, APIError> {
+        let files = self.files()?;
+        for path in &files {
+            fs::remove_file(path)?;
+        }
+        Ok(files.len())
+    }
+
+    /// All cache files
+    ///
+    fn files(&self) -> Result<Vec<PathBuf>, APIError> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut v = vec![];
+        for de in fs::read_dir(&self.dir)? {
+            let path = de?.path();
+            if path.extension().is_some_and(|e| e == EXT) {
+                v.push(path);
+            }
+        }
+        Ok(v)
+    }
+
+    /// File for the given URL
+    ///
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.{}", fnv1a(url.as_bytes()), EXT))
+    }
+
+    fn get(&self, url: &str) -> Option<Entry> {
+        read_entry(&self.path(url)).filter(|e| e.url == url)
+    }
+
+    fn put(&self, e: &Entry) -> Result<(), APIError> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(&e.url), serde_json::to_string(e)?)?;
+        Ok(())
+    }
+
+    /// Remove all entries for URLs starting with `prefix`, whatever their query
+    ///
+    fn remove_prefix(&self, prefix: &str) -> Result<(), APIError> {
+        for path in self.files()? {
+            let e = read_entry(&path);
+            if e.is_none_or(|e| e.url.starts_with(prefix)) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Transport answering from the cache when possible
+///
+#[derive(Debug)]
+pub struct CacheTransport {
+    /// Where requests really go
+    inner: Arc<dyn Transport>,
+    /// Storage
+    cache: Cache,
+    /// Behaviour
+    cfg: CacheConfig,
+}
+
+impl CacheTransport {
+    /// Cache what goes through `inner`
+    ///
+    pub fn new(inner: Arc<dyn Transport>, cfg: CacheConfig) -> Self {
+        CacheTransport {
+            inner,
+            cache: Cache::new(&cfg.dir),
+            cfg,
+        }
+    }
+
+    /// TTL for the context of this URL, if any
+    ///
+    fn ttl(&self, req: &HttpRequest) -> Option<Duration> {
+        let ctx = context(req.url.path())?;
+        if SECRET_PATHS.contains(&ctx) {
+            return None;
+        }
+        self.cfg.ttls.get(ctx).copied().filter(|t| !t.is_zero())
+    }
+}
+
+impl Transport for CacheTransport {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+        let url = normalise(&req.url);
+        let ttl = self.ttl(&req);
+
+        // Changes make our copies obsolete, with any option
+        if req.method != Method::Get {
+            if self.cfg.offline {
+                return Err(offline(&url));
+            }
+            let mut base = req.url.clone();
+            base.set_query(None);
+            self.cache.remove_prefix(&normalise(&base))?;
+            return self.inner.send(req);
+        }
+
+        // Never read from or written to disk
+        let ttl = match (ttl, self.cfg.offline) {
+            (Some(ttl), _) => ttl,
+            (None, false) => return self.inner.send(req),
+            (None, true) if context(req.url.path()).is_some_and(|c| SECRET_PATHS.contains(&c)) => {
+                return Err(offline(&url))
+            }
+            (None, true) => Duration::ZERO,
+        };
+
+        let cached = self.cache.get(&url);
+        if self.cfg.offline {
+            return match cached {
+                Some(e) => Ok(HttpResponse::new(200, e.body.into_bytes())),
+                None => Err(offline(&url)),
+            };
+        }
+
+        let now = now();
+        let mut req = req;
+        if let Some(e) = &cached {
+            if e.expires > now {
+                return Ok(HttpResponse::new(200, e.body.clone().into_bytes()));
+            }
+            if let Some(etag) = &e.etag {
+                req = req.header("If-None-Match", etag);
+            }
+            if let Some(lm) = &e.last_modified {
+                req = req.header("If-Modified-Since", lm);
+            }
+        }
+
+        let mut resp = self.inner.send(req)?;
+        match (resp.status, cached) {
+            // Still good
+            (304, Some(mut e)) => {
+                e.stored = now;
+                e.expires = now + ttl.as_secs();
+                self.cache.put(&e)?;
+                Ok(HttpResponse::new(200, e.body.into_bytes()))
+            }
+            (200, _) => {
+                let mut body = String::new();
+                resp.body.read_to_string(&mut body)?;
+
+                let e = Entry {
+                    url,
+                    stored: now,
+                    expires: now + ttl.as_secs(),
+                    etag: resp.header("etag").map(|s| s.to_string()),
+                    last_modified: resp.header("last-modified").map(|s| s.to_string()),
+                    body,
+                };
+                self.cache.put(&e)?;
+
+                let mut r = HttpResponse::new(200, e.body.into_bytes());
+                r.headers = resp.headers;
+                Ok(r)
+            }
+            _ => Ok(resp),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Context of an API path, i.e. what follows the version (`/api/v2/probes/1/` → `probes`)
+///
+fn context(path: &str) -> Option<&str> {
+    let mut it = path.split('/').filter(|s| !s.is_empty());
+    it.find(|s| s.starts_with('v') && s[1..].parse::<u32>().is_ok())?;
+    it.next()
+}
+
+/// Error returned for what we can not do offline
+///
+fn offline(url: &str) -> APIError {
+    APIError::new(504, "Offline", &format!("{} not in cache", url), "cache")
+}
+
+/// Read an entry, ignoring broken files
+///
+fn read_entry(path: &Path) -> Option<Entry> {
+    let txt = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&txt).ok()
+}
+
+/// Current POSIX time
+///
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Stable hash for file names (FNV-1a, 64 bits)
+///
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use reqwest::Url;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Counts calls, answers with an ETag and honours If-None-Match
+    #[derive(Debug, Default)]
+    struct Origin {
+        calls: Mutex<Vec<Option<String>>>,
+    }
+
+    impl Transport for Origin {
+        fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+            let inm = req
+                .headers
+                .iter()
+                .find(|(k, _)| k == "If-None-Match")
+                .map(|(_, v)| v.clone());
+            self.calls.lock().unwrap().push(inm.clone());
+
+            let mut r = match inm {
+                Some(_) => HttpResponse::new(304, vec![]),
+                None => HttpResponse::new(200, br#"{"id":1}"#.to_vec()),
+            };
+            r.headers.push(("etag".to_string(), "\"v1\"".to_string()));
+            Ok(r)
+        }
+    }
+
+    fn setup(ttl: Duration) -> (Arc<Origin>, CacheTransport, TempDir) {
+        let dir = TempDir::new().unwrap();
+
+        let origin = Arc::new(Origin::default());
+        let cfg = CacheConfig::new(dir.path()).ttl("probes", ttl);
+        (origin.clone(), CacheTransport::new(origin, cfg), dir)
+    }
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest::get(Url::parse(&format!("https://example.net/api/v2{}", path)).unwrap())
+    }
+
+    #[test]
+    fn test_context() {
+        assert_eq!(Some("probes"), context("/api/v2/probes/1/"));
+        assert_eq!(Some("anchors"), context("/api/v2/anchors/"));
+        assert_eq!(None, context("/foo/"));
+    }
+
+    #[test]
+    fn test_cache_hit() {
+        let (origin, t, dir) = setup(Duration::from_secs(60));
+        let dir = dir.path();
+
+        let req = get("/probes/1/?key=SECRET").header("Authorization", "Key SECRET");
+        let r = t.send(req).unwrap();
+        assert_eq!(r#"{"id":1}"#, r.text().unwrap());
+
+        // Same entry whatever the key
+        let r = t.send(get("/probes/1/?key=OTHER")).unwrap();
+        assert_eq!(r#"{"id":1}"#, r.text().unwrap());
+        assert_eq!(1, origin.calls.lock().unwrap().len());
+
+        // Never saved with the key
+        let st = Cache::new(dir).stats().unwrap();
+        assert_eq!(1, st.entries);
+        let f = &Cache::new(dir).files().unwrap()[0];
+        let txt = fs::read_to_string(f).unwrap();
+        assert!(txt.contains("key=REDACTED"));
+        assert!(!txt.contains("SECRET"));
+
+        // Not cached
+        t.send(get("/keys/")).unwrap();
+        t.send(get("/keys/")).unwrap();
+        assert_eq!(3, origin.calls.lock().unwrap().len());
+
+        assert_eq!(1, Cache::new(dir).clear().unwrap());
+    }
+
+    #[test]
+    fn test_cache_revalidate() {
+        let (origin, t, dir) = setup(Duration::from_secs(1));
+
+        t.send(get("/probes/1/")).unwrap();
+        // Make it stale
+        let c = Cache::new(dir.path());
+        let url = normalise(&get("/probes/1/").url);
+        let mut e = c.get(&url).unwrap();
+        e.expires = 0;
+        c.put(&e).unwrap();
+        assert_eq!(1, c.stats().unwrap().expired);
+
+        let r = t.send(get("/probes/1/")).unwrap();
+        assert_eq!(200, r.status);
+        assert_eq!(r#"{"id":1}"#, r.text().unwrap());
+
+        let calls = origin.calls.lock().unwrap();
+        assert_eq!(vec![None, Some("\"v1\"".to_string())], *calls);
+        assert_eq!(0, c.stats().unwrap().expired);
+    }
+
+    #[test]
+    fn test_cache_invalidate() {
+        let (origin, t, dir) = setup(Duration::from_secs(60));
+
+        t.send(get("/probes/1/")).unwrap();
+        t.send(get("/probes/1/?fields=tags")).unwrap();
+        t.send(get("/probes/10/")).unwrap();
+        let c = Cache::new(dir.path());
+        assert_eq!(3, c.stats().unwrap().entries);
+
+        let url = get("/probes/1/?key=SECRET").url;
+        t.send(HttpRequest::new(Method::Patch, url)).unwrap();
+        let left = c.files().unwrap();
+        assert_eq!(1, left.len());
+        assert!(read_entry(&left[0]).unwrap().url.contains("/probes/10/"));
+        assert_eq!(4, origin.calls.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_cache_keys() {
+        let dir = TempDir::new().unwrap();
+        let origin = Arc::new(Origin::default());
+        let cfg = CacheConfig::new(dir.path()).ttl("keys", Duration::from_secs(60));
+        let t = CacheTransport::new(origin.clone(), cfg);
+
+        t.send(get("/keys/0123-abcd/")).unwrap();
+        t.send(get("/keys/0123-abcd/")).unwrap();
+        assert_eq!(2, origin.calls.lock().unwrap().len());
+        assert!(Cache::new(dir.path()).files().unwrap().is_empty());
+
+        let cfg = CacheConfig::new(dir.path()).offline(true);
+        let t = CacheTransport::new(origin, cfg);
+        assert_eq!(
+            504,
+            t.send(get("/keys/0123-abcd/")).unwrap_err().error.status
+        );
+    }
+
+    #[test]
+    fn test_cache_offline() {
+        let (origin, t, dir) = setup(Duration::from_secs(60));
+        t.send(get("/probes/1/")).unwrap();
+
+        let cfg = CacheConfig::new(dir.path()).offline(true);
+        let t = CacheTransport::new(origin.clone(), cfg);
+
+        assert!(t.send(get("/probes/1/")).is_ok());
+        assert_eq!(504, t.send(get("/probes/2/")).unwrap_err().error.status);
+        assert_eq!(1, origin.calls.lock().unwrap().len());
+    }
+}
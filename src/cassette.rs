@@ -0,0 +1,370 @@
+//! Module to record API calls into a file (a "cassette") and replay them later.
+//!
+//! Recording wraps the current transport and saves every request with its answer.  Headers of
+//! the request (where the API key is) are not saved, a `key` parameter, the UUID in a
+//! `/keys/<uuid>/` path and `uuid` fields in answers are replaced by `REDACTED` so cassettes
+//! can be committed.  Replaying never touches
+//! the network: each request is matched against the recorded ones (method, URL with its
+//! query in any order, body) and gets the recorded answer.  This is how the tests run
+//! offline.
+//!
+//! Example:
+//! ```no_run
+//! use atlas_rs::client::ClientBuilder;
+//! use atlas_rs::core::probes::Probe;
+//!
+//! // Once, with a real key
+//! let c = ClientBuilder::new().api_key("MY-KEY").record("probe.json").build().unwrap();
+//! let p: Probe = c.probe().get(666).unwrap();
+//!
+//! // Then as often as needed, any key will do
+//! let c = ClientBuilder::new().api_key("FOO").replay("probe.json").build().unwrap();
+//! let p: Probe = c.probe().get(666).unwrap();
+//! ```
+//!
+
+// Standard library
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// External crates
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Our crates
+use crate::errors::APIError;
+use crate::transport::{
+    redacted, redacted_path, HttpRequest, HttpResponse, Transport, REDACTED, SECRET_PARAMS,
+};
+
+// ---------------------------------------------------------------------------
+
+/// Fields of an answer never saved (API keys are their own ID)
+const SECRET_FIELDS: [&str; 1] = ["uuid"];
+
+// ---------------------------------------------------------------------------
+
+/// Recorded request
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    /// HTTP method
+    pub method: String,
+    /// URL with the query sorted and secrets redacted
+    pub url: String,
+    /// Body if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// Recorded answer
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedResponse {
+    /// HTTP status
+    pub status: u16,
+    /// Headers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    /// Body, as JSON if it can be decoded and a string otherwise
+    pub body: Value,
+}
+
+/// One request and its answer
+///
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// Content of a cassette file
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Read a cassette file
+    ///
+    pub fn load<P: AsRef<Path>>(fname: P) -> Result<Self, APIError> {
+        let txt = fs::read_to_string(fname.as_ref())?;
+        Ok(serde_json::from_str(&txt)?)
+    }
+
+    /// Write the cassette file
+    ///
+    pub fn save<P: AsRef<Path>>(&self, fname: P) -> Result<(), APIError> {
+        let txt = serde_json::to_string_pretty(self)?;
+        Ok(fs::write(fname.as_ref(), txt)?)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Transport saving every call made through the wrapped one
+///
+#[derive(Debug)]
+pub struct RecordTransport {
+    /// Where requests really go
+    inner: Arc<dyn Transport>,
+    /// Cassette file
+    fname: PathBuf,
+    /// What we have seen so far
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordTransport {
+    /// Record into `fname`, overwriting it
+    ///
+    pub fn new<P: AsRef<Path>>(inner: Arc<dyn Transport>, fname: P) -> Self {
+        RecordTransport {
+            inner,
+            fname: fname.as_ref().to_path_buf(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+}
+
+impl Transport for RecordTransport {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+        let request = recorded(&req);
+        let mut resp = self.inner.send(req)?;
+
+        // We need the whole body to save it
+        let mut body = vec![];
+        resp.body.read_to_end(&mut body)?;
+
+        let mut saved = to_value(&body);
+        scrub(&mut saved);
+        let response = RecordedResponse {
+            status: resp.status,
+            headers: resp.headers.clone(),
+            body: saved,
+        };
+
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette
+            .interactions
+            .push(Interaction { request, response });
+        cassette.save(&self.fname)?;
+
+        let mut r = HttpResponse::new(resp.status, body);
+        r.headers = resp.headers;
+        Ok(r)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Transport answering from a cassette, each interaction is used once in order.
+///
+#[derive(Debug)]
+pub struct ReplayTransport {
+    /// Cassette file, read on first use
+    fname: PathBuf,
+    /// Interactions not used yet
+    left: Mutex<Option<Vec<Interaction>>>,
+}
+
+impl ReplayTransport {
+    /// Replay from `fname`
+    ///
+    pub fn new<P: AsRef<Path>>(fname: P) -> Self {
+        ReplayTransport {
+            fname: fname.as_ref().to_path_buf(),
+            left: Mutex::new(None),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+        let mut left = self.left.lock().unwrap();
+        if left.is_none() {
+            *left = Some(Cassette::load(&self.fname)?.interactions);
+        }
+        let left = left.as_mut().unwrap();
+
+        let want = recorded(&req);
+        let pos = left.iter().position(|i| i.request == want).ok_or_else(|| {
+            APIError::new(
+                404,
+                "Not in cassette",
+                &format!("{} {}", want.method, redacted(&req.url)),
+                "replay",
+            )
+        })?;
+        let found = left.remove(pos).response;
+
+        let body = match found.body {
+            Value::String(s) => s.into_bytes(),
+            v => serde_json::to_vec(&v)?,
+        };
+        let mut r = HttpResponse::new(found.status, body);
+        r.headers = found.headers;
+        Ok(r)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Turn a request into its saved form
+///
+fn recorded(req: &HttpRequest) -> RecordedRequest {
+    RecordedRequest {
+        method: req.method.to_string(),
+        url: normalise(&req.url),
+        body: req.body.as_ref().map(|b| to_value(b)),
+    }
+}
+
+/// Sort the query and hide secrets so URLs can be compared
+///
+pub(crate) fn normalise(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if SECRET_PARAMS.contains(&k.as_ref()) {
+                REDACTED.to_string()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), v)
+        })
+        .collect();
+    pairs.sort();
+
+    let mut url = url.clone();
+    url.set_path(&redacted_path(url.path()));
+    url.set_query(None);
+    if !pairs.is_empty() {
+        url.query_pairs_mut().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Replace every secret field, however deep
+///
+fn scrub(v: &mut Value) {
+    match v {
+        Value::Object(m) => {
+            for (k, v) in m.iter_mut() {
+                if SECRET_FIELDS.contains(&k.as_str()) {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    scrub(v);
+                }
+            }
+        }
+        Value::Array(a) => a.iter_mut().for_each(scrub),
+        _ => (),
+    }
+}
+
+/// JSON if possible, string otherwise
+///
+fn to_value(body: &[u8]) -> Value {
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Answer with the path
+    #[derive(Debug)]
+    struct Echo;
+
+    impl Transport for Echo {
+        fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+            let body = format!(r#"{{"path":"{}"}}"#, req.url.path());
+            Ok(HttpResponse::new(200, body.into_bytes()))
+        }
+    }
+
+    #[test]
+    fn test_normalise() {
+        let url = Url::parse("https://example.net/probes/?status=1&key=SECRET&country=FR").unwrap();
+
+        assert_eq!(
+            "https://example.net/probes/?country=FR&key=REDACTED&status=1",
+            normalise(&url)
+        );
+    }
+
+    #[test]
+    fn test_normalise_keys() {
+        let url = Url::parse("https://example.net/api/v2/keys/0123-abcd/?key=SECRET").unwrap();
+
+        assert_eq!(
+            "https://example.net/api/v2/keys/REDACTED/?key=REDACTED",
+            normalise(&url)
+        );
+    }
+
+    /// Answer like the keys endpoint
+    #[derive(Debug)]
+    struct Keys;
+
+    impl Transport for Keys {
+        fn send(&self, _req: HttpRequest) -> Result<HttpResponse, APIError> {
+            let body = r#"{"results":[{"uuid":"0123-abcd","label":"mine"}],"uuid":"4567-ef"}"#;
+            Ok(HttpResponse::new(200, body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_record_keys() {
+        let dir = TempDir::new().unwrap();
+        let fname = dir.path().join("cassette.json");
+        let url = Url::parse("https://example.net/api/v2/keys/89ab-cdef/").unwrap();
+
+        let rec = RecordTransport::new(Arc::new(Keys), &fname);
+        let r = rec.send(HttpRequest::get(url)).unwrap();
+        // Caller still sees the real answer
+        assert!(r.text().unwrap().contains("0123-abcd"));
+
+        let txt = fs::read_to_string(&fname).unwrap();
+        for uuid in ["89ab-cdef", "0123-abcd", "4567-ef"] {
+            assert!(!txt.contains(uuid));
+        }
+        assert!(txt.contains("/keys/REDACTED/"));
+        assert!(txt.contains("mine"));
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let dir = TempDir::new().unwrap();
+        let fname = dir.path().join("cassette.json");
+        let url = Url::parse("https://example.net/keys/?key=SECRET&a=1").unwrap();
+
+        let rec = RecordTransport::new(Arc::new(Echo), &fname);
+        let r = rec.send(HttpRequest::get(url)).unwrap();
+        assert_eq!("/keys/", r.json::<Value>().unwrap()["path"]);
+
+        let txt = fs::read_to_string(&fname).unwrap();
+        assert!(txt.contains("key=REDACTED"));
+        assert!(!txt.contains("SECRET"));
+
+        // Another key, options in another order
+        let url = Url::parse("https://example.net/keys/?a=1&key=OTHER").unwrap();
+        let play = ReplayTransport::new(&fname);
+        let r = play.send(HttpRequest::get(url.clone())).unwrap();
+        assert_eq!(200, r.status);
+
+        // Used only once
+        let e = play.send(HttpRequest::get(url)).unwrap_err();
+        assert_eq!(404, e.error.status);
+        assert!(!e.error.detail.contains("OTHER"));
+    }
+}
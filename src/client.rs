@@ -28,21 +28,37 @@
 //!
 //! We use [reqwest] as HTTP client.  It has support for everything we need incl. proxy.  We choose
 //! to use the blocking client as most of the time this ought to be enough and it is easier.
+//! It is hidden behind the `Transport` trait so it can be replaced with `ClientBuilder::transport()`.
 //!
 //! [reqwest]: https://crates.io/reqwest/
 //!
 
 // Standard library
-use std::time::Duration;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // External crates
 use anyhow::{anyhow, Result};
-use clap::{crate_name, crate_version};
+use log::{log, trace, Level};
 use reqwest::Url;
 
 // Internal crates
+use crate::cache::{CacheConfig, CacheTransport};
+use crate::cassette::{RecordTransport, ReplayTransport};
+use crate::core::{
+    anchor_measurements::AnchorMeasurement, anchors::Anchor, credits::Credits, keys::Key,
+    measurements::Measurement, participation_requests::ParticipationRequests, probes::Probe,
+};
+use crate::entity::Entity;
+use crate::errors::APIError;
 use crate::option::Options;
 use crate::request::RequestBuilder;
+use crate::transport::{
+    redacted, HttpConfig, HttpRequest, HttpResponse, Method, ReqwestTransport, Transport, REDACTED,
+};
 
 // ---------------------------------------------------------------------------
 
@@ -62,25 +78,6 @@ pub enum AF {
     V46,
 }
 
-/// Represents the different categories aka first level of requests (probes, credits, etc.
-#[derive(Clone, Copy, Debug)]
-pub enum Ctx {
-    None = 0,
-    Anchors,
-    AnchorMeasurements,
-    Credits,
-    Keys,
-    Measurements,
-    ParticipationRequests,
-    Probes,
-}
-
-impl Default for Ctx {
-    fn default() -> Self {
-        Ctx::None
-    }
-}
-
 // ---------------------------------------------------------------------------
 
 /// This is the main `Client` struct.  It holds all the parameters and the HTTP client handle.
@@ -114,7 +111,7 @@ impl Default for Ctx {
 /// # }
 /// ```
 ///
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Client {
     /// Mandatory
     pub(crate) api_key: Option<String>,
@@ -133,8 +130,29 @@ pub struct Client {
     /// Default options
     pub(crate) opts: Options,
 
-    /// Internal state, http client
-    pub(crate) agent: Option<reqwest::blocking::Client>,
+    /// Internal state, how requests are sent
+    pub(crate) agent: Arc<dyn Transport>,
+}
+
+/// Never display the API key
+///
+impl Debug for Client {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("endpoint", &self.endpoint.as_str())
+            .field("default_probe", &self.default_probe)
+            .field("area_type", &self.area_type)
+            .field("area_value", &self.area_value)
+            .field("is_oneoff", &self.is_oneoff)
+            .field("pool_size", &self.pool_size)
+            .field("want_af", &self.want_af)
+            .field("verbose", &self.verbose)
+            .field("tags", &self.tags)
+            .field("opts", &self.opts)
+            .field("agent", &self.agent)
+            .finish()
+    }
 }
 
 /// Default values for Client
@@ -175,9 +193,8 @@ impl Client {
             verbose: false,
             tags: "".to_string(),
             opts: Options::new(),
-            agent: None,
+            agent: Arc::new(ReqwestTransport::default()),
         }
-        .httpclient()
     }
 
     /// Create a ClientBuilder struct and returns it for chained calls
@@ -198,59 +215,64 @@ impl Client {
     // Entities
     //
     #[inline]
-    pub fn anchors(&self) -> RequestBuilder {
-        self.route_to(Ctx::Anchors)
+    pub fn anchors(&self) -> RequestBuilder<Anchor> {
+        self.route_to()
     }
 
     #[inline]
-    pub fn anchor_measurement(&self) -> RequestBuilder {
-        self.route_to(Ctx::AnchorMeasurements)
+    pub fn anchor_measurement(&self) -> RequestBuilder<AnchorMeasurement> {
+        self.route_to()
     }
 
     #[inline]
-    pub fn credits(&self) -> RequestBuilder {
-        self.route_to(Ctx::Credits)
+    pub fn credits(&self) -> RequestBuilder<Credits> {
+        self.route_to()
     }
 
     #[inline]
-    pub fn keys(&self) -> RequestBuilder {
-        self.route_to(Ctx::Keys)
+    pub fn keys(&self) -> RequestBuilder<Key> {
+        self.route_to()
     }
 
     #[inline]
-    pub fn measurement(&self) -> RequestBuilder {
-        unimplemented!()
+    pub fn measurement(&self) -> RequestBuilder<Measurement> {
+        self.route_to()
     }
 
     #[inline]
-    pub fn probe(&self) -> RequestBuilder {
-        self.route_to(Ctx::Probes)
+    pub fn participation_requests(&self) -> RequestBuilder<ParticipationRequests> {
+        self.route_to()
+    }
+
+    #[inline]
+    pub fn probe(&self) -> RequestBuilder<Probe> {
+        self.route_to()
     }
 
     // ---------------------------------------------------------------------
     // Protocols
     //
-    pub fn dns(&self) -> RequestBuilder {
+    pub fn dns(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
-    pub fn http(&self) -> RequestBuilder {
+    pub fn http(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
-    pub fn ntp(&self) -> RequestBuilder {
+    pub fn ntp(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
-    pub fn ping(&self) -> RequestBuilder {
+    pub fn ping(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
-    pub fn tlscert(&self) -> RequestBuilder {
+    pub fn tlscert(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
-    pub fn traceroute(&self) -> RequestBuilder {
+    pub fn traceroute(&self) -> RequestBuilder<Measurement> {
         unimplemented!()
     }
 
@@ -262,29 +284,71 @@ impl Client {
     // ---------------------------------------------------------------------
     // Private functions
 
-    /// Create an instance of the HTTP client and attach it there.  It is called as part of
-    /// `.build()`.
+    /// Create a request carrying our credentials.  The API key is sent in the `Authorization`
+    /// header so it never appears in URLs (and thus in logs, errors or proxies).
     ///
-    fn httpclient(mut self) -> Self {
-        let ag = format!("{}/{}", crate_name!(), crate_version!());
-        let agent = reqwest::blocking::ClientBuilder::new()
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(5))
-            .user_agent(&ag)
-            .build()
-            .unwrap();
-        self.agent = Some(agent);
-        self
+    pub(crate) fn http_request(&self, method: Method, url: Url) -> HttpRequest {
+        let r = HttpRequest::new(method, url);
+        match &self.api_key {
+            Some(key) => r.header("authorization", &format!("Key {}", key)),
+            None => r,
+        }
+    }
+
+    /// Send a request through the transport, logging method, path, page, status and latency
+    /// with secrets redacted.  Logged at the `info` level in verbose mode, `debug` otherwise.
+    ///
+    pub(crate) fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+        let level = if self.verbose {
+            Level::Info
+        } else {
+            Level::Debug
+        };
+        let method = req.method;
+        let path = redacted(&req.url);
+        let page = req
+            .url
+            .query_pairs()
+            .find(|(k, _)| k == "page")
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_else(|| "-".to_string());
+        trace!("request={:?}", req);
+
+        let start = Instant::now();
+        let resp = self.agent.send(req);
+        let latency = start.elapsed().as_millis();
+
+        match &resp {
+            Ok(r) => log!(
+                level,
+                "method={} path={} page={} status={} latency={}ms",
+                method,
+                path,
+                page,
+                r.status,
+                latency
+            ),
+            Err(e) => log!(
+                level,
+                "method={} path={} page={} error=\"{}\" latency={}ms",
+                method,
+                path,
+                page,
+                e.error.title,
+                latency
+            ),
+        }
+        resp
     }
 
     /// Private routing function for first level (`probe()`, `keys()`, etc.)
     ///
-    fn route_to(&self, op: Ctx) -> RequestBuilder {
+    fn route_to<E: Entity>(&self) -> RequestBuilder<E> {
         let url = self.endpoint.to_owned();
 
         // Default HTTP operation is GET, some will be POST/DELETE but that is handled in the
         // next call in the chain.
-        let r = reqwest::blocking::Request::new(reqwest::Method::GET, url);
+        let r = self.http_request(Method::Get, url);
 
         // Enforce API key usage
         if self.api_key.is_none() {
@@ -294,14 +358,7 @@ impl Client {
         let mut c = self.clone();
         c.opts.merge(&self.opts);
 
-        // Ensure api-Key is filled in prior to the calls.
-        c.opts["key"] = self.api_key.as_ref().unwrap().clone();
-        RequestBuilder {
-            ctx: op,
-            paged: false,
-            c,
-            r,
-        }
+        RequestBuilder::new(c, r)
     }
 }
 
@@ -329,9 +386,19 @@ impl Client {
 /// # }
 /// ```
 ///
-
+#[derive(Clone)]
 pub struct ClientBuilder {
     cl: Client,
+    /// Settings of the default transport
+    http: HttpConfig,
+    /// Transport replacing the default one
+    transport: Option<Arc<dyn Transport>>,
+    /// Cassette to answer from
+    replay: Option<PathBuf>,
+    /// Cassette to record into
+    record: Option<PathBuf>,
+    /// On-disk cache settings
+    cache: Option<CacheConfig>,
 }
 
 /// Default values for `ClientBuilder`
@@ -359,16 +426,46 @@ impl ClientBuilder {
     /// ```
     ///
     pub fn new() -> Self {
-        ClientBuilder { cl: Client::new() }
+        ClientBuilder {
+            cl: Client::new(),
+            http: HttpConfig::default(),
+            transport: None,
+            replay: None,
+            record: None,
+            cache: None,
+        }
     }
 
-    /// Create the final Client after checking the API key has been changed
+    /// Create the final Client after checking the API key has been changed and the HTTP
+    /// settings are valid.
+    ///
+    /// The transport is put together here, whatever the order of the calls: the cassette
+    /// given to `replay()`, the one given to `transport()` or the default one created from
+    /// the HTTP settings, then `record()` around it and `cache()` around everything.
     ///
     pub fn build(self) -> Result<Client> {
-        match &self.cl.api_key {
-            Some(_k) => Ok(self.cl.clone()),
-            None => Err(anyhow!("You must change the default key")),
+        if self.cl.api_key.is_none() {
+            return Err(anyhow!("You must change the default key"));
+        }
+
+        let mut agent: Arc<dyn Transport> = match (&self.replay, self.transport) {
+            (Some(fname), _) => Arc::new(ReplayTransport::new(fname)),
+            (None, Some(t)) => t,
+            (None, None) => match ReqwestTransport::with_config(&self.http) {
+                Ok(t) => Arc::new(t),
+                Err(e) => return Err(anyhow!("Bad HTTP settings: {}", e.error.detail)),
+            },
+        };
+        if let Some(fname) = self.record {
+            agent = Arc::new(RecordTransport::new(agent, fname));
+        }
+        if let Some(cfg) = self.cache {
+            agent = Arc::new(CacheTransport::new(agent, cfg));
         }
+
+        let mut cl = self.cl;
+        cl.agent = agent;
+        Ok(cl)
     }
 
     /// Set the API key
@@ -490,7 +587,8 @@ impl ClientBuilder {
         self
     }
 
-    /// Sets the verbose flag
+    /// Sets the verbose flag: every request is logged (through the `log` crate) at the `info`
+    /// level instead of `debug`.
     ///
     /// Example:
     ///
@@ -543,6 +641,169 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the time allowed to establish the connection (default 10s)
+    ///
+    /// The HTTP settings (`connect_timeout()`, `timeout()`, `proxy()`, `ca_file()` and
+    /// `user_agent()`) are only used by the default transport, not with `transport()` or
+    /// `replay()`.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .connect_timeout(Duration::from_secs(30))
+    /// # ;
+    /// ```
+    ///
+    pub fn connect_timeout(mut self, v: Duration) -> Self {
+        self.http.connect_timeout = v;
+        self
+    }
+
+    /// Sets the time allowed for a whole request including its body.  The default is zero,
+    /// meaning no limit, so that large result downloads are not cut off
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .timeout(Duration::from_secs(300))
+    /// # ;
+    /// ```
+    ///
+    pub fn timeout(mut self, v: Duration) -> Self {
+        self.http.timeout = v;
+        self
+    }
+
+    /// Use an explicit HTTP, HTTPS or SOCKS5 proxy instead of the `HTTP(S)_PROXY` variables
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .proxy("socks5://localhost:1080")
+    /// # ;
+    /// ```
+    ///
+    pub fn proxy(mut self, v: &str) -> Self {
+        self.http.proxy = Some(v.to_string());
+        self
+    }
+
+    /// Trust the CA certificates from this PEM file as well
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .ca_file("/etc/ssl/corporate-ca.pem")
+    /// # ;
+    /// ```
+    ///
+    pub fn ca_file<P: AsRef<Path>>(mut self, v: P) -> Self {
+        self.http.ca_file = Some(v.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add something to our `User-Agent` header, like the name of your application
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .user_agent("my-monitoring/1.2")
+    /// # ;
+    /// ```
+    ///
+    pub fn user_agent(mut self, v: &str) -> Self {
+        self.http.user_agent = Some(v.to_string());
+        self
+    }
+
+    /// Use another way to send requests than the default `reqwest` client
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::transport::ReqwestTransport;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .transport(ReqwestTransport::new(reqwest::blocking::Client::new()))
+    /// # ;
+    /// ```
+    ///
+    pub fn transport<T: Transport + 'static>(mut self, t: T) -> Self {
+        self.transport = Some(Arc::new(t));
+        self
+    }
+
+    /// Save every call made through the transport into a cassette file, with the API key
+    /// redacted.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .record("probes.json")
+    /// # ;
+    /// ```
+    ///
+    pub fn record<P: AsRef<Path>>(mut self, fname: P) -> Self {
+        self.record = Some(fname.as_ref().to_path_buf());
+        self
+    }
+
+    /// Answer every call from a cassette file, nothing goes to the network
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .replay("probes.json")
+    /// # ;
+    /// ```
+    ///
+    pub fn replay<P: AsRef<Path>>(mut self, fname: P) -> Self {
+        self.replay = Some(fname.as_ref().to_path_buf());
+        self
+    }
+
+    /// Keep answers in an on-disk cache, see `CacheConfig` for the details.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::cache::CacheConfig;
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new()
+    ///     .cache(CacheConfig::new("/tmp/atlas-cache"))
+    /// # ;
+    /// ```
+    ///
+    pub fn cache(mut self, cfg: CacheConfig) -> Self {
+        self.cache = Some(cfg);
+        self
+    }
+
     /// Add options
     ///
     /// Example:
@@ -557,14 +818,16 @@ impl ClientBuilder {
     /// ```
     ///
     pub fn with(&self, opts: &Options) -> Self {
-        let mut cl = self.cl.clone();
-        cl.opts.merge(opts);
-        ClientBuilder { cl }
+        let mut cb = self.clone();
+        cb.cl.opts.merge(opts);
+        cb
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use tempfile::TempDir;
+
     use super::*;
 
     #[test]
@@ -582,7 +845,7 @@ mod tests {
         assert_eq!(AF::V46, c.want_af);
         assert!(!c.verbose);
         assert_eq!("".to_string(), c.tags);
-        assert!(c.agent.is_some());
+        assert!(format!("{:?}", c.agent).starts_with("ReqwestTransport"));
     }
 
     #[test]
@@ -605,7 +868,7 @@ mod tests {
         assert!(!cb.verbose);
         assert_eq!("".to_string(), cb.tags);
         assert!(!cb.opts.contains_key("key"));
-        assert!(cb.agent.is_some());
+        assert!(format!("{:?}", cb.agent).starts_with("ReqwestTransport"));
     }
 
     #[test]
@@ -640,6 +903,82 @@ mod tests {
         assert_eq!("FOO", key.unwrap());
     }
 
+    #[test]
+    fn test_key_in_header() {
+        let c = ClientBuilder::new().api_key("SECRET").build().unwrap();
+        let rb = c.probe();
+
+        assert!(!rb.r.url.as_str().contains("SECRET"));
+        assert!(!rb.c.opts.contains_key("key"));
+        assert_eq!(
+            vec![("authorization".to_string(), "Key SECRET".to_string())],
+            rb.r.headers
+        );
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let c = ClientBuilder::new().api_key("SECRET").build().unwrap();
+        let s = format!("{:?}", c);
+
+        assert!(!s.contains("SECRET"));
+        assert!(s.contains("REDACTED"));
+
+        let s = format!("{:?}", c.probe().r);
+        assert!(!s.contains("SECRET"));
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .connect_timeout(Duration::from_secs(30))
+            .timeout(Duration::ZERO)
+            .user_agent("test/1.0")
+            .proxy("http://localhost:3128");
+
+        assert_eq!(Duration::from_secs(30), c.http.connect_timeout);
+        assert_eq!(Some("test/1.0".to_string()), c.http.user_agent);
+        assert!(c.build().is_ok());
+    }
+
+    #[test]
+    fn test_transport_order() {
+        let dir = TempDir::new().unwrap();
+
+        // HTTP settings after the layers do not drop them
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .cache(CacheConfig::new(dir.path()))
+            .record(dir.path().join("cassette.json"))
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let s = format!("{:?}", c.agent);
+        assert!(s.starts_with("CacheTransport"));
+        assert!(s.contains("RecordTransport"));
+        assert!(s.contains("ReqwestTransport"));
+
+        // Replay always wins over the network
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(dir.path().join("cassette.json"))
+            .proxy("http://localhost:3128")
+            .build()
+            .unwrap();
+        assert!(format!("{:?}", c.agent).starts_with("ReplayTransport"));
+    }
+
+    #[test]
+    fn test_http_settings_error() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .ca_file("/nonexistent.pem")
+            .build();
+
+        assert!(c.is_err());
+    }
+
     #[test]
     fn test_onoff() {
         let c = ClientBuilder::new().api_key("key").onoff(true).build();
@@ -7,24 +7,36 @@
 // External crates
 use anyhow::Result;
 use lazy_regex::regex;
-use reqwest::StatusCode;
+use log::trace;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 // Our crates
 use crate::client::Client;
+use crate::entity::Entity;
 use crate::errors::APIError;
-use crate::request::Op;
+use crate::transport::{HttpResponse, Method};
 
-/// This trait enables routing for types based on the allowed operations
+/// Characters to escape in a single path segment
 ///
-pub trait Routing<T>
-where
-    T: std::fmt::Display,
-{
-    /// Returns the proper URL for the given operation
-    ///
-    fn set_url(op: Op, p: T) -> String;
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Percent-encode a value to be used as one segment of a path, like a tag name
+///
+pub(crate) fn path_segment(s: impl std::fmt::Display) -> String {
+    utf8_percent_encode(&s.to_string(), PATH_SEGMENT).to_string()
 }
 
 /// When asking for a list of S, this generic struct is used for pagination
@@ -34,13 +46,59 @@ pub struct List<S> {
     /// How many results in this block
     pub count: u32,
     /// URL to fetch the next block
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub next: String,
     /// URL to fetch previous block
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub previous: String,
     /// Current key block
+    #[serde(rename = "results")]
     pub data: Vec<S>,
 }
 
+/// Some objects refer to others either through their URL or, when asked with `include=`,
+/// by embedding the whole object.
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Link<T> {
+    /// Bare URL of the object
+    Url(String),
+    /// Expanded object
+    Object(Box<T>),
+}
+
+impl<T: Entity> Link<T> {
+    /// Extract the object ID from the URL (the last path component)
+    ///
+    /// Example:
+    /// ```
+    /// # use atlas_rs::common::Link;
+    /// # use atlas_rs::core::anchors::Anchor;
+    /// # use atlas_rs::ids::AnchorId;
+    /// let l: Link<Anchor> = Link::Url("https://atlas.ripe.net/api/v2/anchors/666/".to_string());
+    ///
+    /// assert_eq!(Some(AnchorId(666)), l.id());
+    /// ```
+    ///
+    pub fn id(&self) -> Option<T::Id> {
+        match self {
+            Link::Url(u) => u.trim_end_matches('/').rsplit('/').next()?.parse().ok(),
+            Link::Object(_) => None,
+        }
+    }
+}
+
+/// The API returns `null` for `next` and `previous` on the first and last pages, map these
+/// to an empty string.
+///
+fn null_as_empty<'de, D>(d: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(d)?.unwrap_or_default())
+}
+
 /// Implement a generic fetch_one_page() function
 ///
 /// Example:
@@ -63,35 +121,112 @@ impl Client {
     where
         S: DeserializeOwned,
     {
-        let url = format!("{}&page={}", url, page);
-
-        let resp = self.agent.as_ref().unwrap().get(&url).send();
-
-        let resp = match resp {
-            Ok(resp) => resp,
-            Err(e) => {
-                let aerr = APIError::new(
-                    e.status().unwrap().as_u16(),
-                    "Bad",
-                    e.to_string().as_str(),
-                    "fetch_one_page",
-                );
-                return Err(aerr);
-            }
-        };
+        let mut url = reqwest::Url::parse(&url)
+            .map_err(|e| APIError::new(400, "Bad URL", &e.to_string(), "fetch_one_page"))?;
+        url.query_pairs_mut().append_pair("page", &page.to_string());
+
+        let resp = self.send(self.http_request(Method::Get, url))?;
 
         // Try to see if we got an error
-        match resp.status() {
-            StatusCode::OK => {
-                // We could use Response::json() here but it consumes the body.
-                let r = resp.text()?;
-                println!("p={}", r);
-                let p: List<S> = serde_json::from_str(&r)?;
-                Ok(p)
+        if resp.is_success() {
+            let r = resp.text()?;
+            trace!("page={} body={} bytes", page, r.len());
+            let p: List<S> = serde_json::from_str(&r)?;
+            Ok(p)
+        } else {
+            let aerr = resp.json::<APIError>()?;
+            Err(aerr)
+        }
+    }
+}
+
+/// Decode the body of a response into `T` or into an `APIError` if the API did not like
+/// our request.
+///
+pub(crate) fn decode_response<T>(resp: HttpResponse) -> Result<T, APIError>
+where
+    T: DeserializeOwned,
+{
+    if resp.is_success() {
+        let r = resp.text()?;
+        Ok(serde_json::from_str(&r)?)
+    } else {
+        let aerr = resp.json::<APIError>()?;
+        Err(aerr)
+    }
+}
+
+// -------------------------------------------------------------------------
+
+/// Lazy iterator over all the elements of a paginated list, pages are fetched only when
+/// needed so very long lists (like the probe archive) are never fully in memory.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::common::Pages;
+/// # use atlas_rs::core::probes::Probe;
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+/// let url = "https://atlas.ripe.net/api/v2/probes/".to_string();
+///
+/// for p in Pages::<Probe>::new(&c, url) {
+///     println!("{}", p.unwrap().id);
+/// }
+/// ```
+///
+#[derive(Debug)]
+pub struct Pages<T> {
+    /// Client for API calls
+    c: Client,
+    /// Base URL of the list
+    url: String,
+    /// Next page to fetch, 0 when done
+    page: usize,
+    /// Current block
+    block: std::vec::IntoIter<T>,
+}
+
+impl<T> Pages<T>
+where
+    T: DeserializeOwned,
+{
+    /// Setup the iterator, nothing is fetched yet
+    ///
+    pub fn new(c: &Client, url: String) -> Self {
+        Pages {
+            c: c.clone(),
+            url,
+            page: 1,
+            block: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<T> Iterator for Pages<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T, APIError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.block.next() {
+                return Some(Ok(e));
+            }
+            if self.page == 0 {
+                return None;
             }
-            _ => {
-                let aerr = resp.json::<APIError>()?;
-                Err(aerr)
+            match self.c.fetch_one_page::<T>(self.url.clone(), self.page) {
+                Ok(rawlist) => {
+                    self.page = get_page_num(rawlist.next);
+                    self.block = rawlist.data.into_iter();
+                }
+                Err(e) => {
+                    // Stop there
+                    self.page = 0;
+                    return Some(Err(e));
+                }
             }
         }
     }
@@ -129,7 +264,7 @@ pub fn get_page_num(url: String) -> usize {
 mod tests {
     use rstest::rstest;
 
-    use crate::common::get_page_num;
+    use crate::common::{get_page_num, List};
 
     #[rstest]
     #[case("", 0)]
@@ -140,4 +275,15 @@ mod tests {
     fn test_get_page_num(#[case] url: &str, #[case] n: usize) {
         assert_eq!(n, get_page_num(url.to_string()));
     }
+
+    #[test]
+    fn test_list_null_next() {
+        let txt = r#"{"count":1,"next":null,"previous":null,"results":[42]}"#;
+        let l: List<u32> = serde_json::from_str(txt).unwrap();
+
+        assert_eq!(1, l.count);
+        assert!(l.next.is_empty());
+        assert!(l.previous.is_empty());
+        assert_eq!(vec![42], l.data);
+    }
 }
@@ -7,15 +7,22 @@
 //                                         ----- /get  ----- AM
 
 // Std library
-use std::fmt;
-use std::fmt::{Display, Formatter};
 
 // External crates
 use serde::{Deserialize, Serialize};
 
 // Our own crates
-use crate::common::Routing;
+use crate::client::Client;
+use crate::common::Link;
+use crate::core::anchors::Anchor;
+use crate::core::measurements::Measurement;
+use crate::entity::{json_display, Entity};
+use crate::errors::APIError;
+use crate::ids::{AnchorId, AnchorMeasurementId};
+use crate::kinds::MeasurementType;
+use crate::option::Options;
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
@@ -24,37 +31,151 @@ use crate::request::Op;
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AnchorMeasurement {
     /// Creation date
-    date_created: String,
+    pub date_created: Timestamp,
     /// Last modification date
-    date_modified: String,
+    pub date_modified: Timestamp,
     /// ID of the target Measurement
-    id: u32,
-    is_mesh: bool,
-    /// URL of the actual measurement
-    measurement: String,
-    /// URL of the anchor to which the measurement is targeted
-    target: String,
+    pub id: AnchorMeasurementId,
+    pub is_mesh: bool,
+    /// The actual measurement, its URL unless `include=measurement` was given
+    pub measurement: Link<Measurement>,
+    /// The anchor to which the measurement is targeted, its URL unless `include=target`
+    /// was given
+    pub target: Link<Anchor>,
     /// Measurement type of the involved measurement
     #[serde(rename = "type")]
-    mtype: String,
+    pub mtype: MeasurementType,
 }
 
-/// Implement the Display trait.
-///
-impl Display for AnchorMeasurement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
+json_display!(AnchorMeasurement);
+
+impl AnchorMeasurement {
+    /// Return both the targeted `Anchor` and the `Measurement`, using the expanded objects
+    /// if present and fetching them otherwise.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::anchor_measurements::AnchorMeasurement;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let am: AnchorMeasurement = c.anchor_measurement().get(1234).unwrap();
+    /// let (anchor, msm) = am.resolve(&c).unwrap();
+    /// ```
+    ///
+    pub fn resolve(&self, c: &Client) -> Result<(Anchor, Measurement), APIError> {
+        let anchor = match &self.target {
+            Link::Object(a) => *a.clone(),
+            Link::Url(_) => c.anchors().get(link_id(&self.target, "target")?)?,
+        };
+        let msm = match &self.measurement {
+            Link::Object(m) => *m.clone(),
+            Link::Url(_) => c
+                .measurement()
+                .get(link_id(&self.measurement, "measurement")?)?,
+        };
+        Ok((anchor, msm))
     }
 }
 
-impl<T: Display> Routing<T> for AnchorMeasurement {
-    /// Generate the proper URL for the service we want in the given category
+/// Get the ID out of a link or complain
+///
+fn link_id<T: Entity>(l: &Link<T>, what: &str) -> Result<T::Id, APIError> {
+    l.id()
+        .ok_or_else(|| APIError::new(500, "Bad link", "no ID in URL", what))
+}
+
+// -------------------------------------------------------------------------
+
+/// Filters available when listing anchor measurements, to be given to `with()`.  Use
+/// `include()` on the request to expand the target and measurement links.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::anchor_measurements::{AnchorMeasurement, AnchorMeasurementFilter};
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let res: Vec<AnchorMeasurement> = c.anchor_measurement()
+///     .with(AnchorMeasurementFilter::new().target(666))
+///     .include(&["target", "measurement"])
+///     .list()
+///     .unwrap();
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct AnchorMeasurementFilter(Options);
+
+impl AnchorMeasurementFilter {
+    /// Empty filter
     ///
-    fn set_url(op: Op, uuid: T) -> String {
-        match op {
-            Op::Get => format!("/anchor-measurements/{}/", uuid), // /get
-            Op::List => "/anchor-measurements/".to_string(),      // /list
-            _ => panic!("not possible"),
-        }
+    pub fn new() -> Self {
+        AnchorMeasurementFilter(Options::new())
+    }
+
+    /// Only measurements targeting this anchor
+    ///
+    pub fn target(mut self, anchor: impl Into<AnchorId>) -> Self {
+        self.0["target"] = anchor.into().to_string();
+        self
+    }
+
+    /// Only measurements of this type
+    ///
+    pub fn mtype(mut self, mtype: impl Into<MeasurementType>) -> Self {
+        self.0["type"] = mtype.into().to_string();
+        self
+    }
+}
+
+impl From<AnchorMeasurementFilter> for Options {
+    fn from(f: AnchorMeasurementFilter) -> Self {
+        f.0
+    }
+}
+
+impl Entity for AnchorMeasurement {
+    type Id = AnchorMeasurementId;
+
+    const PATH: &'static str = "/anchor-measurements/";
+    const OPS: &'static [Op] = &[Op::Get, Op::List];
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::ids::MeasurementId;
+
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let o: Options = AnchorMeasurementFilter::new()
+            .target(666)
+            .mtype(MeasurementType::Ping)
+            .into();
+
+        assert_eq!(Options::from([("target", "666"), ("type", "ping")]), o);
+    }
+
+    #[test]
+    fn test_links() {
+        let txt = r#"{
+            "date_created": "2015-07-01T08:16:14.183542",
+            "date_modified": "2015-07-01T08:16:14.183570",
+            "id": 1234,
+            "is_mesh": true,
+            "measurement": "https://atlas.ripe.net/api/v2/measurements/1790945/",
+            "target": "https://atlas.ripe.net/api/v2/anchors/666/",
+            "type": "ping"
+        }"#;
+        let am: AnchorMeasurement = serde_json::from_str(txt).unwrap();
+
+        assert_eq!(AnchorMeasurementId(1234), am.id);
+        assert_eq!(Some(MeasurementId(1790945)), am.measurement.id());
+        assert_eq!(Some(AnchorId(666)), am.target.id());
     }
 }
@@ -8,84 +8,161 @@
 
 // -------------------------------------------------------------------------
 // Standard library
-use std::fmt;
-use std::fmt::{Display, Formatter};
 
 // External crates
 use serde::{Deserialize, Serialize};
 
 // Our crates
-use crate::common::Routing;
 use crate::core::probes::Geometry;
+use crate::entity::{json_display, Entity};
+use crate::ids::{AnchorId, ProbeId};
+use crate::kinds::ObjectType;
+use crate::option::Options;
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
-/// Struct describing all data about a given anchor
+/// Struct describing all data about a given anchor, fields not asked for with `fields()`
+/// are `None`.
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Anchor {
-    /// The id of the anchor, XXX
-    pub id: i64,
+    /// The id of the anchor
+    pub id: AnchorId,
     /// The type of the object,
     #[serde(rename = "type")]
-    pub atype: String,
+    pub atype: Option<ObjectType>,
     /// The fully qualified domain name of the anchor,
-    pub fqdn: String,
+    pub fqdn: Option<String>,
     /// The id of the probe that is hosted on this anchor,
-    pub probe: i64,
+    pub probe: Option<ProbeId>,
     /// Is it IPv4-only?
-    pub is_ipv4_only: bool,
+    pub is_ipv4_only: Option<bool>,
     /// The IPv4 address (if any) of this anchor,
     pub ip_v4: Option<String>,
     /// The IPv4 AS this anchor belongs to,
-    pub as_v4: i64,
+    pub as_v4: Option<i64>,
     /// The IPv4 gateway address of this anchor,
-    pub ip_v4_gateway: String,
+    pub ip_v4_gateway: Option<String>,
     /// The IPv4 netmask for the IP address of this anchor,
-    pub ip_v4_netmask: String,
+    pub ip_v4_netmask: Option<String>,
     /// The IPv6 address (if any) of this anchor,
     pub ip_v6: Option<String>,
     /// The IPv6 AS this anchor belongs to,
-    pub as_v6: i64,
+    pub as_v6: Option<i64>,
     /// The IPv6 gateway address of this anchor,
-    pub ip_v6_gateway: String,
+    pub ip_v6_gateway: Option<String>,
     /// The IPv6 prefix of this anchor, XXX
-    pub ip_v6_prefix: i64,
+    pub ip_v6_prefix: Option<i64>,
     /// The city this anchor is located in,
-    pub city: String,
+    pub city: Option<String>,
     /// An ISO-3166-1 alpha-2 code indicating the country that this probe is located in, as
     /// derived from the user supplied longitude and latitude,
-    pub country: String,
+    pub country: Option<String>,
     /// A GeoJSON point object containing the location of this anchor. The longitude and latitude
     /// are contained within the `coordinates` array, XXX
-    pub geometry: Geometry,
+    pub geometry: Option<Geometry>,
     /// Installed TLSA DNS resource record on this anchor,
-    pub tlsa_record: String,
+    pub tlsa_record: Option<String>,
     /// Is it disabled?
-    pub is_disabled: bool,
+    pub is_disabled: Option<bool>,
     /// Date the achor went live,
-    pub date_live: String,
+    pub date_live: Option<Timestamp>,
     /// Version [ 0, 1, 2, 99 ]
-    pub hardware_version: i32,
+    pub hardware_version: Option<i32>,
 }
 
-/// Implement the Display trait.
+json_display!(Anchor);
+
+// -------------------------------------------------------------------------
+
+/// Filters available when listing anchors, to be given to `with()`.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::anchors::{Anchor, AnchorFilter};
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
 ///
-impl Display for Anchor {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
+/// let res: Vec<Anchor> = c.anchors()
+///     .with(AnchorFilter::new().country("FR").asn_v4(3215))
+///     .list()
+///     .unwrap();
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct AnchorFilter(Options);
+
+impl AnchorFilter {
+    /// Empty filter
+    ///
+    pub fn new() -> Self {
+        AnchorFilter(Options::new())
     }
-}
 
-impl<T: Display> Routing<T> for Anchor {
-    /// Generate the proper URL for the service we want in the given category
+    /// Only anchors in this country (ISO 3166 code)
     ///
-    fn set_url(op: Op, id: T) -> String {
-        match op {
-            Op::Get => format!("/anchors/{}/", id), // /get
-            Op::List => "/anchors/".to_string(),    // /list
-            _ => panic!("not possible"),
-        }
+    pub fn country(mut self, cc: &str) -> Self {
+        self.0["country"] = cc.to_string();
+        self
+    }
+
+    /// Only anchors in this IPv4 AS
+    ///
+    pub fn asn_v4(mut self, asn: u32) -> Self {
+        self.0["as_v4"] = asn.to_string();
+        self
+    }
+
+    /// Only anchors in this IPv6 AS
+    ///
+    pub fn asn_v6(mut self, asn: u32) -> Self {
+        self.0["as_v6"] = asn.to_string();
+        self
+    }
+
+    /// Search anchors by FQDN, city or country
+    ///
+    pub fn search(mut self, fqdn: &str) -> Self {
+        self.0["search"] = fqdn.to_string();
+        self
+    }
+}
+
+impl From<AnchorFilter> for Options {
+    fn from(f: AnchorFilter) -> Self {
+        f.0
+    }
+}
+
+// -------------------------------------------------------------------------
+
+impl Entity for Anchor {
+    type Id = AnchorId;
+
+    const PATH: &'static str = "/anchors/";
+    const OPS: &'static [Op] = &[Op::Get, Op::List];
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor_filter() {
+        let o: Options = AnchorFilter::new()
+            .country("FR")
+            .asn_v6(3215)
+            .search("ripe.net")
+            .into();
+
+        assert_eq!(
+            Options::from([("country", "FR"), ("as_v6", "3215"), ("search", "ripe.net")]),
+            o
+        );
     }
 }
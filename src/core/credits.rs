@@ -14,15 +14,15 @@
 
 // -------------------------------------------------------------------------
 // Standard library
-use std::fmt;
-use std::fmt::{Display, Formatter};
 
 // External crates
 use serde::{Deserialize, Serialize};
 
-use crate::common::Routing;
 // Our crates
+use crate::entity::{json_display, Entity};
+use crate::ids::{MeasurementId, NoId, ProbeId};
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
@@ -43,7 +43,7 @@ pub struct Credits {
     /// Estimated daily income minus estimated daily expenditure
     pub estimated_daily_balance: u32,
     /// Time that the estimates were produced
-    pub calculation_time: String,
+    pub calculation_time: Timestamp,
     /// Estimated elapsed time from `calculation_time` until total credit balance will drop to zero
     pub estimated_runout_seconds: Option<u32>,
     /// Number of results from user-defined measurements in the past 24 hours
@@ -62,13 +62,7 @@ pub struct Credits {
     pub transactions: String,
 }
 
-/// Implement the Display trait.
-///
-impl Display for Credits {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
-    }
-}
+json_display!(Credits);
 
 // -------------------------------------------------------------------------
 
@@ -84,7 +78,7 @@ pub struct ClaimRequests {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProbeIncome {
     /// ID of the probe,
-    pub probe_id: u32,
+    pub probe_id: ProbeId,
     /// URL of the probe in the probes API,
     pub probe: String,
     /// Description of this income item,
@@ -100,7 +94,7 @@ pub struct ProbeIncome {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HostedProbeIncome {
     /// ID of the probe,
-    pub probe_id: u32,
+    pub probe_id: ProbeId,
     /// URL of the probe in the probes API,
     pub probe: String,
     /// Description of this income item,
@@ -134,15 +128,15 @@ pub struct IncomeGroups {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MeasurementExpense {
     /// ID of the measurement.
-    pub measurement_id: u32,
+    pub measurement_id: MeasurementId,
     /// URL of the measurement in the measurements API,
     pub measurement: String,
     /// Description of this expense item,
     pub description: String,
     /// Time when this measurement started or will start,
-    pub scheduled_start_time: String,
+    pub scheduled_start_time: Timestamp,
     /// Time when this measurement is scheduled to stop, if at all,
-    pub scheduled_stop_time: String,
+    pub scheduled_stop_time: Option<Timestamp>,
     /// Amount of time in the next 24 hours that this measurement is scheduled to be running,
     pub next_day_running_time_seconds: u32,
     /// Best available estimate of number of participating probes,
@@ -201,9 +195,9 @@ pub struct MemberListing {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ExpenseGroup {
     /// Measurements scheduled by and billed to you,
-    owned_measurements: Vec<MeasurementExpense>,
+    pub owned_measurements: Vec<MeasurementExpense>,
     /// Measurements scheduled by other users that are billed to you,
-    billed_measurements: Vec<MeasurementExpense>,
+    pub billed_measurements: Vec<MeasurementExpense>,
 }
 
 /// Struct to hold all expense items
@@ -216,19 +210,58 @@ pub struct ExpenseItems {
     pub total_estimated_daily_expenditure: u32,
 }
 
-impl<T: Display> Routing<T> for Credits {
-    /// Generate the proper URL for the service we want in the given category
+impl ExpenseItems {
+    /// Find the estimated daily cost of a given measurement
     ///
-    fn set_url(op: Op, _nothing: T) -> String {
-        match op {
-            Op::Info => "/credits/".to_string(),                      // /get
-            Op::Incomes => "/credits/incomes/".to_string(),           // /get
-            Op::Expenses => "/credits/expenses/".to_string(),         // /get
-            Op::Transfers => "/credits/transfers/".to_string(),       // /get
-            Op::Transactions => "/credits/transactions/".to_string(), // /get
-            Op::Members => "/credits/members/".to_string(),           // /get
-            Op::Claim => "/credits/members/claim/".to_string(),       // /create
-            _ => panic!("not possible"),
-        }
+    pub fn daily_cost(&self, msm: MeasurementId) -> Option<u32> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.owned_measurements.iter().chain(&g.billed_measurements))
+            .find(|e| e.measurement_id == msm)
+            .map(|e| e.estimated_daily_cost)
+    }
+}
+
+// -------------------------------------------------------------------------
+
+impl Entity for Credits {
+    /// Credits are per account, there is no ID
+    type Id = NoId;
+
+    const PATH: &'static str = "/credits/";
+    const OPS: &'static [Op] = &[
+        Op::Info,
+        Op::Incomes,
+        Op::Expenses,
+        Op::Transfers,
+        Op::Transactions,
+        Op::Members,
+        Op::Claim,
+    ];
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::client::ClientBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_get_credits() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/credits.json"
+            ))
+            .build()
+            .unwrap();
+
+        let cr: Credits = c.credits().info().unwrap();
+
+        assert_eq!(1000000, cr.current_balance);
+        assert_eq!(None, cr.estimated_runout_seconds);
     }
 }
@@ -13,18 +13,19 @@
 
 // -------------------------------------------------------------------------
 // Standard library
-use std::fmt;
-use std::fmt::{Display, Formatter};
 
-#[cfg(feature = "flat-api")]
-use reqwest::StatusCode;
 // External crates
 use serde::{Deserialize, Serialize};
 
 // Our crates
 use crate::client::Client;
-use crate::common::Routing;
+use crate::entity::{json_display, Entity};
+#[cfg(feature = "flat-api")]
+use crate::errors::APIError;
+use crate::ids::KeyUuid;
+use crate::kinds::KeyType;
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
@@ -35,33 +36,27 @@ use crate::request::Op;
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Key {
     /// Main ID is an uuid
-    pub uuid: String,
+    pub uuid: KeyUuid,
     /// Key validity from...
-    pub valid_from: Option<String>,
+    pub valid_from: Option<Timestamp>,
     /// Key validity to
-    pub valid_to: Option<String>,
+    pub valid_to: Option<Timestamp>,
     /// Is this an usable key?
     pub enabled: bool,
     ///  Is it an active one?
     pub is_active: bool,
     /// Creation date
-    pub created_at: String,
+    pub created_at: Timestamp,
     /// Key label (see atlas.ripe.net)
     pub label: String,
     /// Entitlements for the key
     pub grants: Vec<Grant>,
     /// Key type
     #[serde(rename = "type")]
-    pub ktype: String,
+    pub ktype: KeyType,
 }
 
-/// Implement the Display trait.
-///
-impl Display for Key {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
-    }
-}
+json_display!(Key);
 
 // -------------------------------------------------------------------------
 
@@ -104,81 +99,60 @@ impl Client {
     /// Examples:
     ///
     /// ```no_run
-    ///  # use atlas_rs::client::Client;
-    ///  # use atlas_rs::core::keys::Key;
+    ///  # use atlas_rs::client::ClientBuilder;
+    ///  # use atlas_rs::ids::KeyUuid;
     ///
-    ///     let cl = Client::new().verbose(true);
-    ///     let pi = cl.get_key("key-id").unwrap();
+    ///     let cl = ClientBuilder::new().api_key("foo").build().unwrap();
+    ///     let uuid: KeyUuid = "d1e1a2b3-0123-4567-89ab-cdef01234567".parse().unwrap();
+    ///     let pi = cl.get_key(uuid).unwrap();
     ///
     ///     println!("key ID {}: {}", pi.uuid, pi.label);
     ///  ```
     ///
     #[cfg(feature = "flat-api")]
-    pub fn get_key(&self, uuid: &str) -> Result<Key, APIError> {
-        let opts = &self.opts.clone();
-        let url = format!("{}/keys/{}/", self.endpoint, uuid);
-        let url = add_opts(&url, opts);
-
-        let resp = self.agent.as_ref().unwrap().get(&url).send();
-
-        let resp = match resp {
-            Ok(resp) => resp,
-            Err(e) => {
-                let aerr = APIError::new(
-                    e.status().unwrap().as_u16(),
-                    "Bad",
-                    e.to_string().as_str(),
-                    "get_key",
-                );
-                return Err(aerr);
-            }
-        };
-
-        // Try to see if we got an error
-        match resp.status() {
-            StatusCode::OK => {
-                // We could use Response::json() here but it consumes the body.
-                let r = resp.text()?;
-                println!("p={}", r);
-                let p: Key = serde_json::from_str(&r)?;
-                Ok(p)
-            }
-            _ => {
-                let aerr = resp.json::<APIError>()?;
-                Err(aerr)
-            }
-        }
+    pub fn get_key(&self, uuid: impl Into<KeyUuid>) -> Result<Key, APIError> {
+        self.get::<Key>(uuid)
     }
 
-    /// Get information about a set of keys according to parameters
+    /// Get information about all our keys
     ///
     #[cfg(feature = "flat-api")]
-    pub fn get_keys() -> Result<Vec<Key>, APIError> {
-        unimplemented!()
+    pub fn get_keys(&self) -> Result<Vec<Key>, APIError> {
+        self.list::<Key>(self.opts.clone())
     }
 }
 
-impl<T: Display> Routing<T> for Key {
-    /// Generate the proper URL for the service we want in the given category
-    ///
-    fn set_url(op: Op, uuid: T) -> String {
-        match op {
-            Op::Permissions => "/keys/permissions/".to_string(), // /permissions
-            Op::Targets => format!("/keys/permissions/{}/targets/", uuid), // /get targets
-            Op::Get => format!("/keys/{}/", uuid),               // /get
-            Op::Set => format!("/keys/{}/", uuid),               // /set
-            Op::Delete => format!("/keys/{}/", uuid),            // /delete
-            Op::List => "/keys/".to_string(),                    // /list
-            Op::Create => "/keys/".to_string(),                  // /create
-            _ => panic!("not possible"),
-        }
-    }
+impl Entity for Key {
+    type Id = KeyUuid;
+
+    const PATH: &'static str = "/keys/";
+    const OPS: &'static [Op] = &[Op::Get, Op::List, Op::Create, Op::Set, Op::Delete];
 }
 
 // -------------------------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
+    use crate::client::ClientBuilder;
+
+    use super::*;
+
     #[test]
-    fn test_get_key() {}
+    fn test_get_key() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/keys.json"
+            ))
+            .build()
+            .unwrap();
+
+        // Both pages
+        let keys: Vec<Key> = c.keys().list().unwrap();
+
+        assert_eq!(2, keys.len());
+        assert_eq!("people.add_measurement", keys[0].grants[0].permission);
+        assert!(!keys[1].enabled);
+    }
 }
@@ -9,45 +9,686 @@
 //      ----- /get
 //      ----- /update
 //      ----- /delete
+//      ----- P       ----- /results
+//                    ----- /latest
+//                    ----- /status-check
 
 // -------------------------------------------------------------------------
 // Standard library
-use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::collections::BTreeSet;
+use std::thread;
+use std::time::{Duration, Instant};
 
 // External crates
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 // Our crates
-use crate::common::Routing;
+use crate::client::Client;
+use crate::core::results::MeasurementResult;
+use crate::entity::{json_display, Entity};
+use crate::errors::APIError;
+use crate::ids::{MeasurementId, ProbeId};
+use crate::kinds::{MeasurementState, MeasurementType};
+use crate::option::Options;
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
-/// Struct describing all data about a given measurement
+/// Current status of a measurement
 ///
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Measurement {}
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MeasurementStatus {
+    /// Status ID
+    pub id: u32,
+    /// Status name: Specified, Scheduled, Ongoing, Stopped, etc.
+    pub name: MeasurementState,
+    /// POSIX time of the last status change
+    pub when: Option<Timestamp>,
+}
+
+/// Probe participating in a measurement, only returned with `optional_fields=probes`
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ProbeRef {
+    /// Probe ID
+    pub id: ProbeId,
+}
+
+/// Struct describing all data about a given measurement, fields not asked for with
+/// `fields()` are `None`.
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Measurement {
+    /// Measurement ID
+    pub id: MeasurementId,
+    /// Inet family, 4 or 6
+    pub af: Option<u32>,
+    /// POSIX time of creation
+    pub creation_time: Option<Timestamp>,
+    /// Free text description
+    pub description: Option<String>,
+    /// Interval between two runs for periodic measurements
+    pub interval: Option<u32>,
+    /// Is it a one-off measurement?
+    pub is_oneoff: Option<bool>,
+    /// Is it public?
+    pub is_public: Option<bool>,
+    /// Number of probes which sent at least one result
+    pub participant_count: Option<u32>,
+    /// Number of probes requested
+    pub probes_requested: Option<u32>,
+    /// Number of probes actually scheduled
+    pub probes_scheduled: Option<u32>,
+    /// Participating probes, only with `optional_fields=probes`
+    pub probes: Option<Vec<ProbeRef>>,
+    /// Is the target resolved on each probe?
+    pub resolve_on_probe: Option<bool>,
+    /// URL of the results
+    pub result: Option<String>,
+    /// POSIX start time
+    pub start_time: Option<Timestamp>,
+    /// Current status
+    pub status: Option<MeasurementStatus>,
+    /// POSIX stop time
+    pub stop_time: Option<Timestamp>,
+    /// Target name
+    pub target: Option<String>,
+    /// Target AS
+    pub target_asn: Option<u32>,
+    /// Target IP address
+    pub target_ip: Option<String>,
+    /// User-defined tags
+    pub tags: Option<Vec<String>>,
+    /// Measurement type (ping, traceroute, etc.)
+    #[serde(rename = "type")]
+    pub mtype: Option<MeasurementType>,
+}
+
+json_display!(Measurement);
+
+// -------------------------------------------------------------------------
+
+/// Filters available when listing measurements, to be given to `with()`.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::measurements::{Measurement, MeasurementFilter};
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let res: Vec<Measurement> = c.probe()
+///     .with(MeasurementFilter::new().mtype("ping").af(6))
+///     .measurements(666)
+///     .unwrap()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct MeasurementFilter(Options);
+
+impl MeasurementFilter {
+    /// Empty filter
+    ///
+    pub fn new() -> Self {
+        MeasurementFilter(Options::new())
+    }
+
+    /// Only measurements of this type (ping, traceroute, etc.)
+    ///
+    pub fn mtype(mut self, mtype: impl Into<MeasurementType>) -> Self {
+        self.0["type"] = mtype.into().to_string();
+        self
+    }
+
+    /// Only measurements with this status, unknown ones have no ID to filter on and are
+    /// ignored
+    ///
+    pub fn status(mut self, status: impl Into<MeasurementState>) -> Self {
+        if let Some(id) = status.into().id() {
+            self.0.insert("status", id);
+        }
+        self
+    }
+
+    /// Only measurements for this inet family (4 or 6)
+    ///
+    pub fn af(mut self, af: u32) -> Self {
+        self.0["af"] = af.to_string();
+        self
+    }
+
+    /// Only public or private measurements
+    ///
+    pub fn is_public(mut self, p: bool) -> Self {
+        self.0["is_public"] = p.to_string();
+        self
+    }
+}
+
+impl From<MeasurementFilter> for Options {
+    fn from(f: MeasurementFilter) -> Self {
+        f.0
+    }
+}
+
+// -------------------------------------------------------------------------
+
+/// Select measurements to stop in bulk.  Tag, target and group are sent to the API, the
+/// description regex is checked locally.  The owner is always the account of the API key
+/// (`mine=true`), the API has no way to select another one and we never want to stop
+/// measurements of other users that happen to be visible.  A selector without any other
+/// filter would match every running measurement of the account so `stop_measurements()`
+/// refuses it.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::measurements::MeasurementSelector;
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let sel = MeasurementSelector::new().description("^test-").unwrap();
+/// let plan = c.stop_measurements(&sel, true).unwrap();
+/// println!("would save {} credits/day", plan.daily_credits);
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct MeasurementSelector {
+    /// User tag
+    tag: Option<String>,
+    /// Regex on the description
+    description: Option<Regex>,
+    /// Target name or address
+    target: Option<String>,
+    /// Only those created together with this one
+    group: Option<MeasurementId>,
+}
+
+impl MeasurementSelector {
+    /// Select all running measurements
+    ///
+    pub fn new() -> Self {
+        MeasurementSelector::default()
+    }
+
+    /// Only measurements with this tag
+    ///
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Only measurements whose description matches the regex
+    ///
+    pub fn description(mut self, re: &str) -> Result<Self, APIError> {
+        let re = Regex::new(re)
+            .map_err(|e| APIError::new(400, "Bad regex", &e.to_string(), "description"))?;
+        self.description = Some(re);
+        Ok(self)
+    }
+
+    /// Only measurements towards this target
+    ///
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// Only measurements of this group, i.e. created in the same request (the group ID is
+    /// the ID of the first one)
+    ///
+    pub fn group(mut self, id: MeasurementId) -> Self {
+        self.group = Some(id);
+        self
+    }
+
+    /// Is there no filter at all?
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none()
+            && self.description.is_none()
+            && self.target.is_none()
+            && self.group.is_none()
+    }
+
+    /// Does the measurement match?
+    ///
+    pub fn matches(&self, m: &Measurement) -> bool {
+        let tag = match &self.tag {
+            Some(t) => m.tags.iter().flatten().any(|x| x == t),
+            None => true,
+        };
+        let descr = match &self.description {
+            Some(re) => re.is_match(m.description.as_deref().unwrap_or("")),
+            None => true,
+        };
+        let target = match &self.target {
+            Some(t) => m.target.as_deref() == Some(t.as_str()) || m.target_ip.as_deref() == Some(t),
+            None => true,
+        };
+        tag && descr && target
+    }
+}
+
+impl From<&MeasurementSelector> for Options {
+    /// Filters given to the API, only our running measurements are interesting
+    ///
+    fn from(sel: &MeasurementSelector) -> Self {
+        let mut o = Options::from([("mine", "true")]);
+        o.insert_list(
+            "status__in",
+            MeasurementState::RUNNING.iter().filter_map(|s| s.id()),
+        );
+        if let Some(t) = &sel.tag {
+            o["tags"] = t.clone();
+        }
+        if let Some(t) = &sel.target {
+            o["target"] = t.clone();
+        }
+        if let Some(g) = sel.group {
+            o.insert("group_id", g);
+        }
+        o
+    }
+}
+
+/// One measurement to be stopped
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct StopItem {
+    /// Measurement ID
+    pub id: MeasurementId,
+    /// Free text description
+    pub description: Option<String>,
+    /// Target name
+    pub target: Option<String>,
+    /// Estimated daily cost, if known
+    pub daily_cost: Option<u32>,
+}
+
+/// Result of a bulk stop.  With `dry_run` nothing is stopped and `stopped` is empty.
+///
+#[derive(Debug)]
+pub struct StopReport {
+    /// Measurements matching the selector
+    pub items: Vec<StopItem>,
+    /// Estimated daily credits saved, only counting the measurements actually stopped
+    /// (all of them with `dry_run`)
+    pub daily_credits: u32,
+    /// Result for each measurement we tried to stop
+    pub stopped: Vec<(MeasurementId, Result<(), APIError>)>,
+}
+
+/// Bulk operations on measurements
+///
+impl Client {
+    /// Stop every running measurement matching the selector.  With `dry_run`, only return
+    /// what would be stopped and the estimated daily credits saved.  An empty selector is
+    /// an error.
+    ///
+    pub fn stop_measurements(
+        &self,
+        sel: &MeasurementSelector,
+        dry_run: bool,
+    ) -> Result<StopReport, APIError> {
+        if sel.is_empty() {
+            return Err(APIError::new(
+                400,
+                "Empty selector",
+                "refusing to stop every running measurement, give at least one filter",
+                "stop_measurements",
+            ));
+        }
+
+        let list: Vec<Measurement> = self.measurement().with(sel).list()?;
+
+        // Not fatal, we just will not know the cost
+        let expenses = self.credits().expenses().ok();
+
+        let items: Vec<StopItem> = list
+            .into_iter()
+            .filter(|m| sel.matches(m))
+            .map(|m| StopItem {
+                id: m.id,
+                daily_cost: expenses.as_ref().and_then(|e| e.daily_cost(m.id)),
+                description: m.description,
+                target: m.target,
+            })
+            .collect();
+
+        let stopped: Vec<(MeasurementId, Result<(), APIError>)> = if dry_run {
+            vec![]
+        } else {
+            items
+                .iter()
+                .map(|i| (i.id, self.measurement().delete(i.id)))
+                .collect()
+        };
+
+        let daily_credits = items
+            .iter()
+            .filter(|i| dry_run || stopped.iter().any(|(id, r)| *id == i.id && r.is_ok()))
+            .filter_map(|i| i.daily_cost)
+            .sum();
 
-/// Implement the Display trait.
+        Ok(StopReport {
+            items,
+            daily_credits,
+            stopped,
+        })
+    }
+}
+
+// -------------------------------------------------------------------------
+
+/// When to stop waiting for a measurement
 ///
-impl Display for Measurement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
+#[derive(Clone, Debug)]
+pub struct WaitOpts {
+    /// Fraction of the participants (0.0 to 1.0) which must have answered
+    pub fraction: f64,
+    /// Give up after this long
+    pub timeout: Duration,
+    /// Time between two polls
+    pub interval: Duration,
+}
+
+impl Default for WaitOpts {
+    fn default() -> Self {
+        WaitOpts {
+            fraction: 1.0,
+            timeout: Duration::from_secs(600),
+            interval: Duration::from_secs(10),
+        }
     }
 }
 
-impl<T: Display> Routing<T> for Measurement {
-    /// Generate the proper URL for the service we want in the given category
+/// What we got after waiting for a measurement
+///
+#[derive(Debug)]
+pub struct Completion {
+    /// Last known state of the measurement
+    pub measurement: Measurement,
+    /// All results collected
+    pub results: Vec<MeasurementResult>,
+    /// Probes which were scheduled but never answered, `None` if the API did not tell us
+    /// which probes were scheduled
+    pub missing: Option<Vec<ProbeId>>,
+    /// Did we give up?
+    pub timed_out: bool,
+}
+
+impl Completion {
+    /// Gather the state of the measurement and the results so far
+    ///
+    pub fn new(measurement: Measurement, results: Vec<MeasurementResult>, timed_out: bool) -> Self {
+        let answered: BTreeSet<ProbeId> = results.iter().map(|r| r.prb_id()).collect();
+        let missing = measurement.probes.as_ref().map(|probes| {
+            probes
+                .iter()
+                .map(|p| p.id)
+                .filter(|id| !answered.contains(id))
+                .collect()
+        });
+        Completion {
+            measurement,
+            results,
+            missing,
+            timed_out,
+        }
+    }
+
+    /// Fraction of the participants which answered, 0.0 if we do not know how many were
+    /// scheduled yet.
     ///
-    fn set_url(op: Op, uuid: T) -> String {
-        match op {
-            Op::Create => unimplemented!(),
-            Op::Delete => unimplemented!(),
-            Op::Get => format!("/measurements/{}/", uuid), // /get
-            Op::List => "/measurements/".to_string(),      // /list
-            Op::Update => unimplemented!(),
-            _ => panic!("not possible"),
+    pub fn answered(&self) -> f64 {
+        let answered: BTreeSet<ProbeId> = self.results.iter().map(|r| r.prb_id()).collect();
+        let expected = match &self.measurement.probes {
+            Some(p) if !p.is_empty() => p.len() as u32,
+            _ => self.measurement.probes_scheduled.unwrap_or(0),
+        };
+        if expected == 0 {
+            return 0.0;
         }
+        answered.len() as f64 / expected as f64
+    }
+
+    /// Is the measurement over (stopped, failed, etc.)?
+    ///
+    pub fn is_stopped(&self) -> bool {
+        self.measurement
+            .status
+            .as_ref()
+            .is_some_and(|s| s.name.is_over())
+    }
+}
+
+/// Helpers for one-off measurements
+///
+impl Client {
+    /// Poll a measurement until it is stopped, enough of the participants have reported
+    /// or the timeout is reached.  Each poll only asks for the results since the last one
+    /// we got.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::measurements::WaitOpts;
+    /// # use atlas_rs::ids::MeasurementId;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let opts = WaitOpts { fraction: 0.9, timeout: Duration::from_secs(300), ..Default::default() };
+    /// let done = c.wait_for(MeasurementId(1001), &opts).unwrap();
+    ///
+    /// println!("{} results, no answer from {:?}", done.results.len(), done.missing.unwrap_or_default());
+    /// ```
+    ///
+    pub fn wait_for(&self, msm: MeasurementId, opts: &WaitOpts) -> Result<Completion, APIError> {
+        let deadline = Instant::now() + opts.timeout;
+        let mut results: Vec<MeasurementResult> = vec![];
+        let mut seen = BTreeSet::new();
+
+        loop {
+            let m: Measurement = self.measurement().optional_fields(&["probes"]).get(msm)?;
+
+            // `start` is inclusive so the last second is asked again, duplicates are dropped
+            let rb = self.measurement();
+            let new = match results.iter().map(|r| r.timestamp()).max() {
+                Some(last) => rb.with([("start", last.secs())]).results(msm)?,
+                None => rb.results(msm)?,
+            };
+            results.extend(
+                new.into_iter()
+                    .filter(|r| seen.insert((r.prb_id(), r.timestamp()))),
+            );
+
+            let now = Instant::now();
+            let timed_out = now >= deadline;
+            let done = Completion::new(m, results, timed_out);
+
+            if done.is_stopped() || done.answered() >= opts.fraction || timed_out {
+                return Ok(done);
+            }
+            results = done.results;
+            thread::sleep(opts.interval.min(deadline - now));
+        }
+    }
+}
+
+// -------------------------------------------------------------------------
+
+impl Entity for Measurement {
+    type Id = MeasurementId;
+
+    const PATH: &'static str = "/measurements/";
+    const OPS: &'static [Op] = &[
+        Op::Get,
+        Op::List,
+        Op::Create,
+        Op::Update,
+        Op::Delete,
+        Op::Results,
+        Op::Latest,
+        Op::StatusCheck,
+    ];
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::client::ClientBuilder;
+    use crate::core::results::MeasurementResult;
+
+    use super::*;
+
+    #[test]
+    fn test_get_measurement() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/measurements.json"
+            ))
+            .build()
+            .unwrap();
+
+        let m: Measurement = c.measurement().get(1001).unwrap();
+        assert_eq!(Some(MeasurementType::Ping), m.mtype);
+        assert_eq!(Some(vec!["root".to_string()]), m.tags);
+
+        let r: Vec<MeasurementResult> = c.measurement().results(1001).unwrap();
+        assert_eq!(2, r.len());
+        assert_eq!(1234, r[1].prb_id());
+
+        assert!(c.measurement().delete(1001).is_ok());
+    }
+
+    #[test]
+    fn test_measurement_filter() {
+        let o: Options = MeasurementFilter::new()
+            .mtype("ping")
+            .status(MeasurementState::Ongoing)
+            .af(4)
+            .is_public(true)
+            .into();
+
+        assert_eq!(
+            Options::from([
+                ("type", "ping"),
+                ("status", "2"),
+                ("af", "4"),
+                ("is_public", "true")
+            ]),
+            o
+        );
+    }
+
+    fn measurement(id: u32, descr: &str, tags: &[&str]) -> Measurement {
+        let txt = format!(
+            r#"{{"id":{},"description":"{}","tags":{:?},"is_oneoff":false,"is_public":true,
+                "status":{{"id":2,"name":"Ongoing"}},"target":"example.com","type":"ping"}}"#,
+            id, descr, tags
+        );
+        serde_json::from_str(&txt).unwrap()
+    }
+
+    #[test]
+    fn test_selector_matches() {
+        let m = measurement(1, "test-foo", &["lab"]);
+
+        assert!(MeasurementSelector::new().matches(&m));
+        assert!(MeasurementSelector::new().is_empty());
+        assert!(!MeasurementSelector::new().tag("lab").is_empty());
+        assert!(MeasurementSelector::new().tag("lab").matches(&m));
+        assert!(!MeasurementSelector::new().tag("prod").matches(&m));
+        assert!(MeasurementSelector::new()
+            .description("^test-")
+            .unwrap()
+            .matches(&m));
+        assert!(!MeasurementSelector::new()
+            .description("bar$")
+            .unwrap()
+            .target("example.com")
+            .matches(&m));
+        assert!(MeasurementSelector::new().description("(").is_err());
+    }
+
+    #[test]
+    fn test_stop_empty_selector() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/measurements.json"
+            ))
+            .build()
+            .unwrap();
+
+        let e = c
+            .stop_measurements(&MeasurementSelector::new(), true)
+            .unwrap_err();
+        assert_eq!("Empty selector", e.error.title);
+    }
+
+    #[test]
+    fn test_completion() {
+        let m: Measurement = serde_json::from_str(
+            r#"{"id":1001,"is_oneoff":true,"is_public":true,"probes_scheduled":3,
+                "probes":[{"id":1},{"id":2},{"id":3}],
+                "status":{"id":2,"name":"Ongoing"},"type":"ping"}"#,
+        )
+        .unwrap();
+        let results: Vec<MeasurementResult> = [1, 3, 3]
+            .iter()
+            .map(|p| {
+                serde_json::from_str(&format!(
+                    r#"{{"fw":5020,"msm_id":1001,"prb_id":{},"timestamp":0,"type":"ping"}}"#,
+                    p
+                ))
+                .unwrap()
+            })
+            .collect();
+
+        let c = Completion::new(m, results, false);
+
+        assert!(!c.is_stopped());
+        assert_eq!(Some(vec![ProbeId(2)]), c.missing);
+        assert!((c.answered() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        let mut m = c.measurement;
+        m.status.as_mut().unwrap().name = MeasurementState::Failed;
+        m.probes = None;
+        let c = Completion::new(m, vec![], false);
+        assert!(c.is_stopped());
+        assert!(c.missing.is_none());
+    }
+
+    #[test]
+    fn test_selector_options() {
+        let o: Options = (&MeasurementSelector::new()
+            .tag("lab")
+            .group(MeasurementId(1001)))
+            .into();
+
+        assert_eq!(
+            Options::from([
+                ("mine", "true"),
+                ("status__in", "0,1,2"),
+                ("tags", "lab"),
+                ("group_id", "1001")
+            ]),
+            o
+        );
+
+        // Never anything but ours
+        let o: Options = (&MeasurementSelector::new().target("example.com")).into();
+        assert_eq!("true", o["mine"]);
     }
 }
@@ -6,3 +6,4 @@ pub mod keys;
 pub mod measurements;
 pub mod participation_requests;
 pub mod probes;
+pub mod results;
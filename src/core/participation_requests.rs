@@ -1,45 +1,215 @@
 //! Structs and methods to deal with participation requests
 //!
+//! Participation requests are the way to add probes to or remove probes from a running
+//! measurement.  Each measurement has a list of these requests, the initial set of
+//! probes being the first ones.
+//!
 
 // We have the following call tree:
 //
-// ----- /participation-requests ----- /list
+// ----- /participation-requests ----- /get
+// ----- /measurements           ----- P     ----- /participation-requests ----- /list
+//                                                                          ----- /create
 
 // -------------------------------------------------------------------------
 // Standard library
-use std::fmt::Display;
 
 // External crates
 use serde::{Deserialize, Serialize};
 
 // Our crates
-use crate::common::Routing;
+use crate::core::measurements::Measurement;
+use crate::entity::{json_display, Entity};
+use crate::ids::{MeasurementId, ParticipationRequestId, ProbeId};
+use crate::kinds::{Action, SelectionType};
 use crate::request::Op;
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
-/// Struct describing all data about a given anchor
+/// Struct describing all data about a given participation request
 ///
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ParticipationRequests {
+    /// How many probes were requested
+    pub requested: u32,
+    /// Type of selection: area, country, prefix, asn, probes or msm
+    #[serde(rename = "type")]
+    pub rtype: SelectionType,
+    /// Value for the given type
+    pub value: String,
+    /// Either `add` or `remove`
+    pub action: Action,
+    /// Probe tags that must be present
+    pub tags_include: Option<String>,
+    /// Probe tags that must not be present
+    pub tags_exclude: Option<String>,
+    /// ID of the request
+    pub id: ParticipationRequestId,
+    /// POSIX time of creation
+    pub created_at: Timestamp,
+}
+
+json_display!(ParticipationRequests);
+
+// -------------------------------------------------------------------------
+
+/// One change to submit to an ongoing measurement, either adding new probes or removing
+/// some of them.
+///
+/// Example:
+/// ```
+/// use atlas_rs::core::participation_requests::ParticipationChange;
+/// use atlas_rs::ids::ProbeId;
+///
+/// let add = ParticipationChange::add_asn(10, 3215).tags_include("system-ipv6-works");
+/// let del = ParticipationChange::remove(&[ProbeId(666), ProbeId(1234)]);
+/// ```
+///
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ParticipationChange {
+    /// Either `add` or `remove`
+    pub action: Action,
+    /// How many probes
     pub requested: u32,
+    /// Type of selection: area, country, prefix, asn, probes or msm
     #[serde(rename = "type")]
-    pub rtype: String,
+    pub rtype: SelectionType,
+    /// Value for the given type
     pub value: String,
-    pub action: String,
-    pub tags_include: String,
-    pub tags_exclude: String,
-    pub id: u32,
-    pub created_at: u32,
+    /// Probe tags that must be present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags_include: Option<String>,
+    /// Probe tags that must not be present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags_exclude: Option<String>,
 }
 
-impl<T: Display> Routing<T> for ParticipationRequests {
-    /// Generate the proper URL for the service we want in the given category
+impl ParticipationChange {
+    /// Add `n` probes selected by the given type & value.
     ///
-    fn set_url(op: Op, data: T) -> String {
-        match op {
-            Op::Get => format!("/participation-requests/{}/", data), // /list
-            _ => panic!("not possible"),
+    pub fn add(n: u32, rtype: impl Into<SelectionType>, value: &str) -> Self {
+        ParticipationChange {
+            action: Action::Add,
+            requested: n,
+            rtype: rtype.into(),
+            value: value.to_string(),
+            tags_include: None,
+            tags_exclude: None,
         }
     }
+
+    /// Add `n` probes from an area (`WW`, `West`, `North-Central`, etc.)
+    ///
+    pub fn add_area(n: u32, area: &str) -> Self {
+        Self::add(n, SelectionType::Area, area)
+    }
+
+    /// Add `n` probes from the given AS
+    ///
+    pub fn add_asn(n: u32, asn: u32) -> Self {
+        Self::add(n, SelectionType::Asn, &asn.to_string())
+    }
+
+    /// Add `n` probes from a country (ISO 3166 code)
+    ///
+    pub fn add_country(n: u32, cc: &str) -> Self {
+        Self::add(n, SelectionType::Country, cc)
+    }
+
+    /// Remove the given probes from the measurement
+    ///
+    pub fn remove(probes: &[ProbeId]) -> Self {
+        let value = probes
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        ParticipationChange {
+            action: Action::Remove,
+            requested: probes.len() as u32,
+            rtype: SelectionType::Probes,
+            value,
+            tags_include: None,
+            tags_exclude: None,
+        }
+    }
+
+    /// Only select probes with these tags
+    ///
+    pub fn tags_include(mut self, tags: &str) -> Self {
+        self.tags_include = Some(tags.to_string());
+        self
+    }
+
+    /// Never select probes with these tags
+    ///
+    pub fn tags_exclude(mut self, tags: &str) -> Self {
+        self.tags_exclude = Some(tags.to_string());
+        self
+    }
+}
+
+/// Answer from the API after submitting changes
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RequestIds {
+    /// IDs of the new participation requests
+    pub request_ids: Vec<ParticipationRequestId>,
+}
+
+impl Entity for ParticipationRequests {
+    type Id = ParticipationRequestId;
+
+    const PATH: &'static str = "/participation-requests/";
+    const OPS: &'static [Op] = &[Op::Get];
+}
+
+impl ParticipationRequests {
+    /// Path of the requests of a given measurement, this is where they are listed and
+    /// created
+    ///
+    pub(crate) fn measurement_url(msm: &MeasurementId) -> String {
+        format!("{}{}{}", Measurement::PATH, msm, Self::PATH)
+    }
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(
+            "/measurements/1001/participation-requests/",
+            ParticipationRequests::measurement_url(&MeasurementId(1001))
+        );
+        assert_eq!(
+            "/participation-requests/42/",
+            ParticipationRequests::url(Op::Get, Some(&ParticipationRequestId(42))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_change_remove() {
+        let c = ParticipationChange::remove(&[ProbeId(1), ProbeId(2), ProbeId(3)]);
+
+        assert_eq!("remove", c.action);
+        assert_eq!(3, c.requested);
+        assert_eq!("probes", c.rtype);
+        assert_eq!("1,2,3", c.value);
+    }
+
+    #[test]
+    fn test_change_serialize() {
+        let c = ParticipationChange::add_country(5, "FR").tags_include("system-ipv4-works");
+        let s = serde_json::to_string(&c).unwrap();
+
+        assert_eq!(
+            r#"{"action":"add","requested":5,"type":"country","value":"FR","tags_include":"system-ipv4-works"}"#,
+            s
+        );
+    }
 }
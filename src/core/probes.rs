@@ -23,11 +23,10 @@
 
 // std library
 //
-use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
 
-#[cfg(feature = "flat-api")]
-use reqwest::StatusCode;
 // External crates
 //
 use serde::{Deserialize, Serialize};
@@ -35,15 +34,20 @@ use serde::{Deserialize, Serialize};
 // Our crates
 //
 use crate::client::Client;
-use crate::common::Routing;
+use crate::common::path_segment;
+use crate::entity::{json_display, Entity};
+use crate::errors::APIError;
+use crate::ids::ProbeId;
+use crate::kinds::{ObjectType, ProbeStatus};
+#[cfg(any(feature = "flat-api", feature = "alt-api"))]
+use crate::option::Options;
 use crate::request::Op;
-
-// -------------------------------------------------------------------------
+use crate::timestamp::Timestamp;
 
 // -------------------------------------------------------------------------
 
 /// Geolocation as reported by the probe
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Geometry {
     #[serde(rename = "type")]
     pub gtype: String,
@@ -52,19 +56,19 @@ pub struct Geometry {
 }
 
 /// Describes the current status of the probe
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Status {
     /// Date
-    pub since: String,
+    pub since: Timestamp,
     /// Status ID
     pub id: u32,
     /// Status: connected, etc.
-    pub name: String,
+    pub name: ProbeStatus,
 }
 
 /// Tags about the probe, most generated by the API, some you can add
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Tag {
     /// free-text Name like "system: IPv4 works"
     pub name: String,
@@ -74,9 +78,10 @@ pub struct Tag {
 
 /// All information about a given probe
 /// Some fields are `Option` either because in some cases (like without an API key), information
-/// is masked or just could be empty and deserialisation would fail.
+/// is masked or just could be empty and deserialisation would fail.  Fields not asked for
+/// with `fields()` are `None`.
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Probe {
     /// IPv4 address
     pub address_v4: Option<String>,
@@ -87,50 +92,45 @@ pub struct Probe {
     /// IPv6 Autonomous System ID
     pub asn_v6: Option<u32>,
     /// ISO 3166 Country Code
-    pub country_code: String,
+    pub country_code: Option<String>,
     /// Free text description
-    pub description: String,
+    pub description: Option<String>,
     /// First connection
-    pub first_connected: u32,
+    pub first_connected: Option<Timestamp>,
     /// Approx Position
-    pub geometry: Geometry,
+    pub geometry: Option<Geometry>,
     /// Probe ID
-    pub id: u32,
+    pub id: ProbeId,
     /// Is it an Anchor?
-    pub is_anchor: bool,
+    pub is_anchor: Option<bool>,
     /// Is it public?
-    pub is_public: bool,
+    pub is_public: Option<bool>,
     /// POSIX time since last connect
-    pub last_connected: u32,
+    pub last_connected: Option<Timestamp>,
     /// IPv4 Network Prefix
     pub prefix_v4: Option<String>,
     /// IPv6 Network Prefix
     pub prefix_v6: Option<String>,
     /// Probe Status
-    pub status: Status,
+    pub status: Option<Status>,
     /// Integer time
-    pub status_since: u32,
+    pub status_since: Option<Timestamp>,
     /// System and User tags
-    pub tags: Vec<Tag>,
+    pub tags: Option<Vec<Tag>>,
     /// Total uptime
-    pub total_uptime: u32,
+    pub total_uptime: Option<u32>,
     /// Probe Type
     #[serde(rename = "type")]
-    pub ptype: String,
+    pub ptype: Option<ObjectType>,
 }
 
-/// Implement fmt::Display for Probe
-impl Display for Probe {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
-    }
-}
+json_display!(Probe);
 
 // -------------------------------------------------------------------------
 
 /// When asking for a list of probes, this struct is used for pagination
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ProbeList {
     /// How many results in this block
     pub count: u32,
@@ -144,6 +144,441 @@ pub struct ProbeList {
 
 // -------------------------------------------------------------------------
 
+/// Probe as found in the daily archive.  Old snapshots include probes that never connected
+/// or have been abandoned so most fields are optional.
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ArchivedProbe {
+    /// Probe ID
+    pub id: ProbeId,
+    /// IPv4 address
+    pub address_v4: Option<String>,
+    /// IPv6 address
+    pub address_v6: Option<String>,
+    /// IPv4 Autonomous System ID
+    pub asn_v4: Option<u32>,
+    /// IPv6 Autonomous System ID
+    pub asn_v6: Option<u32>,
+    /// ISO 3166 Country Code
+    pub country_code: Option<String>,
+    /// Approx Position
+    pub geometry: Option<Geometry>,
+    /// Is it an Anchor?
+    #[serde(default)]
+    pub is_anchor: bool,
+    /// Is it public?
+    #[serde(default)]
+    pub is_public: bool,
+    /// IPv4 Network Prefix
+    pub prefix_v4: Option<String>,
+    /// IPv6 Network Prefix
+    pub prefix_v6: Option<String>,
+    /// Probe Status
+    pub status: Option<Status>,
+    /// System and User tags
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+json_display!(ArchivedProbe);
+
+/// One value that changed for a given probe between two snapshots
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Changed<T> {
+    /// Probe ID
+    pub id: ProbeId,
+    /// Value in the old snapshot
+    pub before: T,
+    /// Value in the new snapshot
+    pub after: T,
+}
+
+/// Differences between two archive snapshots, every list is sorted by probe ID.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::probes::{ArchiveDiff, ArchivedProbe};
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let old: Vec<ArchivedProbe> = c.probe().archive("2022-05-01").unwrap().collect::<Result<_, _>>().unwrap();
+/// let new: Vec<ArchivedProbe> = c.probe().archive("2022-05-02").unwrap().collect::<Result<_, _>>().unwrap();
+///
+/// let d = ArchiveDiff::new(&old, &new);
+/// println!("{} new probes", d.appeared.len());
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArchiveDiff {
+    /// Probes only present in the new snapshot
+    pub appeared: Vec<ProbeId>,
+    /// Probes only present in the old snapshot
+    pub disappeared: Vec<ProbeId>,
+    /// Probes which moved to another country
+    pub country: Vec<Changed<Option<String>>>,
+    /// Probes which moved to another IPv4 AS
+    pub asn_v4: Vec<Changed<Option<u32>>>,
+    /// Probes which moved to another IPv6 AS
+    pub asn_v6: Vec<Changed<Option<u32>>>,
+    /// Probes whose status changed (connected, disconnected, etc.)
+    pub status: Vec<Changed<Option<ProbeStatus>>>,
+}
+
+impl ArchiveDiff {
+    /// Compare two snapshots
+    ///
+    pub fn new(old: &[ArchivedProbe], new: &[ArchivedProbe]) -> Self {
+        let old: BTreeMap<ProbeId, &ArchivedProbe> = old.iter().map(|p| (p.id, p)).collect();
+        let new: BTreeMap<ProbeId, &ArchivedProbe> = new.iter().map(|p| (p.id, p)).collect();
+
+        let mut d = ArchiveDiff {
+            disappeared: old
+                .keys()
+                .filter(|id| !new.contains_key(id))
+                .copied()
+                .collect(),
+            ..Default::default()
+        };
+
+        for (id, n) in new.iter() {
+            let o = match old.get(id) {
+                Some(o) => o,
+                None => {
+                    d.appeared.push(*id);
+                    continue;
+                }
+            };
+
+            changed(&mut d.country, *id, &o.country_code, &n.country_code);
+            changed(&mut d.asn_v4, *id, &o.asn_v4, &n.asn_v4);
+            changed(&mut d.asn_v6, *id, &o.asn_v6, &n.asn_v6);
+            changed(
+                &mut d.status,
+                *id,
+                &o.status.as_ref().map(|s| s.name.clone()),
+                &n.status.as_ref().map(|s| s.name.clone()),
+            );
+        }
+        d
+    }
+
+    /// Is there any difference?
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty()
+            && self.disappeared.is_empty()
+            && self.country.is_empty()
+            && self.asn_v4.is_empty()
+            && self.asn_v6.is_empty()
+            && self.status.is_empty()
+    }
+}
+
+/// Record a change if the values differ
+///
+fn changed<T: Clone + PartialEq>(v: &mut Vec<Changed<T>>, id: ProbeId, before: &T, after: &T) {
+    if before != after {
+        v.push(Changed {
+            id,
+            before: before.clone(),
+            after: after.clone(),
+        });
+    }
+}
+
+// -------------------------------------------------------------------------
+
+/// Entry in the probe rankings
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Ranking {
+    /// Probe ID
+    pub id: ProbeId,
+    /// Position in the rankings
+    pub rank: Option<u32>,
+    /// Score used for the rankings
+    pub score: Option<f64>,
+    /// Free text description
+    pub description: Option<String>,
+}
+
+json_display!(Ranking);
+
+/// Tag as described in the global tag catalogue
+///
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TagInfo {
+    /// free-text Name like "system: IPv4 works"
+    pub name: String,
+    /// Value like "system-ipv4-works"
+    pub slug: String,
+    /// Either `system` or `user`
+    #[serde(rename = "type")]
+    pub ttype: Option<String>,
+    /// How many probes are using this tag
+    pub total_probes: u32,
+}
+
+json_display!(TagInfo);
+
+/// The global tag catalogue, used to autocomplete and check tag filters before sending
+/// them to the API.
+///
+/// Example:
+/// ```no_run
+/// # use atlas_rs::client::ClientBuilder;
+/// # use atlas_rs::core::probes::TagCatalogue;
+///
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let cat = TagCatalogue::new(c.probe().tags().unwrap());
+///
+/// for t in cat.complete("system-ipv6") {
+///     println!("{} ({} probes)", t.slug, t.total_probes);
+/// }
+/// assert!(cat.unknown("+system-ipv6-works !nat").is_empty());
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct TagCatalogue {
+    /// All tags, by slug
+    tags: BTreeMap<String, TagInfo>,
+}
+
+impl TagCatalogue {
+    /// Create the catalogue from a list of tags
+    ///
+    pub fn new(tags: Vec<TagInfo>) -> Self {
+        TagCatalogue {
+            tags: tags.into_iter().map(|t| (t.slug.clone(), t)).collect(),
+        }
+    }
+
+    /// Get a tag by its slug
+    ///
+    pub fn get(&self, slug: &str) -> Option<&TagInfo> {
+        self.tags.get(slug)
+    }
+
+    /// Return all the tags whose slug or name starts with the given prefix, most used
+    /// ones first.
+    ///
+    pub fn complete(&self, prefix: &str) -> Vec<&TagInfo> {
+        let prefix = prefix.to_lowercase();
+        let mut v: Vec<&TagInfo> = self
+            .tags
+            .values()
+            .filter(|t| t.slug.starts_with(&prefix) || t.name.to_lowercase().starts_with(&prefix))
+            .collect();
+        v.sort_by_key(|t| std::cmp::Reverse(t.total_probes));
+        v
+    }
+
+    /// Check a tag filter (same format as `ClientBuilder::tags()`) and return the slugs
+    /// which are not in the catalogue.
+    ///
+    pub fn unknown(&self, filter: &str) -> Vec<String> {
+        filter
+            .split_whitespace()
+            .map(|t| t.trim_start_matches(['+', '-', '!']))
+            .filter(|t| !t.is_empty() && !self.tags.contains_key(*t))
+            .map(|t| t.to_string())
+            .collect()
+    }
+
+    /// Number of tags
+    ///
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Is it empty?
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+// -------------------------------------------------------------------------
+
+/// Changes to apply to a probe you own, only the fields which are set are sent.
+///
+/// Example:
+/// ```
+/// use atlas_rs::core::probes::ProbeUpdate;
+///
+/// let u = ProbeUpdate::new()
+///     .description("Home probe")
+///     .tags(&["home", "ftth"])
+///     .location(48.85, 2.35);
+/// ```
+///
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ProbeUpdate {
+    /// Free text description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// User tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Is it public?
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_public: Option<bool>,
+    /// Position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<Geometry>,
+}
+
+impl ProbeUpdate {
+    /// Nothing to change yet
+    ///
+    pub fn new() -> Self {
+        ProbeUpdate::default()
+    }
+
+    /// Set the description
+    ///
+    pub fn description(mut self, d: &str) -> Self {
+        self.description = Some(d.to_string());
+        self
+    }
+
+    /// Set the user tags, replacing the current ones
+    ///
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        self
+    }
+
+    /// Make the probe public or not
+    ///
+    pub fn is_public(mut self, p: bool) -> Self {
+        self.is_public = Some(p);
+        self
+    }
+
+    /// Set the position, GeoJSON wants the longitude first
+    ///
+    pub fn location(mut self, latitude: f64, longitude: f64) -> Self {
+        self.geometry = Some(Geometry {
+            gtype: "Point".to_string(),
+            coordinates: vec![longitude, latitude],
+        });
+        self
+    }
+}
+
+/// One line of a bulk update file, in either CSV or TOML.  Tags are space-separated in CSV.
+///
+#[derive(Debug, Deserialize)]
+struct UpdateLine {
+    id: ProbeId,
+    description: Option<String>,
+    #[serde(default, deserialize_with = "tag_list")]
+    tags: Option<Vec<String>>,
+    is_public: Option<bool>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+/// TOML bulk update files are a list of `[[probe]]` tables
+///
+#[derive(Debug, Deserialize)]
+struct UpdateFile {
+    probe: Vec<UpdateLine>,
+}
+
+/// Accept either a list of tags (TOML) or a space-separated string (CSV)
+///
+fn tag_list<'de, D>(d: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        S(String),
+        L(Vec<String>),
+    }
+
+    Ok(match Option::<Tags>::deserialize(d)? {
+        None => None,
+        Some(Tags::S(s)) if s.trim().is_empty() => None,
+        Some(Tags::S(s)) => Some(s.split_whitespace().map(|t| t.to_string()).collect()),
+        Some(Tags::L(l)) => Some(l),
+    })
+}
+
+impl TryFrom<UpdateLine> for (ProbeId, ProbeUpdate) {
+    type Error = APIError;
+
+    fn try_from(l: UpdateLine) -> Result<Self, Self::Error> {
+        let mut u = ProbeUpdate {
+            description: l.description.filter(|d| !d.is_empty()),
+            tags: l.tags,
+            is_public: l.is_public,
+            geometry: None,
+        };
+        match (l.latitude, l.longitude) {
+            (Some(lat), Some(lon)) => u = u.location(lat, lon),
+            (None, None) => (),
+            _ => {
+                return Err(APIError::new(
+                    400,
+                    "Bad location",
+                    &format!("probe {}: need both latitude and longitude", l.id),
+                    "load_updates",
+                ))
+            }
+        }
+        Ok((l.id, u))
+    }
+}
+
+/// Load a set of probe changes from a CSV or TOML file, the format is chosen from the
+/// extension.
+///
+/// CSV files have a header line with `id,description,tags,is_public,latitude,longitude`, empty
+/// cells are left unchanged:
+///
+/// ```csv
+/// id,description,tags,is_public,latitude,longitude
+/// 666,Home probe,home ftth,true,,
+/// 667,,,false,48.85,2.35
+/// ```
+///
+/// TOML files have one `[[probe]]` table per probe with the same keys:
+///
+/// ```toml
+/// [[probe]]
+/// id = 666
+/// description = "Home probe"
+/// tags = ["home", "ftth"]
+/// ```
+///
+pub fn load_updates<P: AsRef<Path>>(fname: P) -> Result<Vec<(ProbeId, ProbeUpdate)>, APIError> {
+    let fname = fname.as_ref();
+    let lines: Vec<UpdateLine> = match fname.extension().and_then(|e| e.to_str()) {
+        Some("csv") => csv::Reader::from_path(fname)?
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+        Some("toml") => toml::from_str::<UpdateFile>(&fs::read_to_string(fname)?)?.probe,
+        _ => {
+            return Err(APIError::new(
+                400,
+                "Unknown format",
+                "bulk files must be .csv or .toml",
+                "load_updates",
+            ))
+        }
+    };
+    lines.into_iter().map(|l| l.try_into()).collect()
+}
+
+// -------------------------------------------------------------------------
+
 /// Methods associated with probes.
 ///
 impl Probe {
@@ -154,13 +589,13 @@ impl Probe {
     /// # use atlas_rs::core::probes::Probe;
     /// # use atlas_rs::client::ClientBuilder;
     ///
-    /// let c = ClientBuilder::new().api_key("the-key")?;
-    /// let p = Probe::get(cl, 666)?;
+    /// let cl = ClientBuilder::new().api_key("the-key").build().unwrap();
+    /// let p = Probe::get(&cl, 666).unwrap();
     /// ```
     ///
     #[cfg(feature = "alt-api")]
-    pub fn get(cl: &Client, pn: u32) -> Result<Self, APIError> {
-        Ok(cl.probe().get(pn).call()?)
+    pub fn get(cl: &Client, pn: impl Into<ProbeId>) -> Result<Self, APIError> {
+        cl.get::<Probe>(pn)
     }
 
     /// Alternate API for probes
@@ -169,14 +604,15 @@ impl Probe {
     /// ```no_run
     /// # use atlas_rs::core::probes::Probe;
     /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::option::Options;
     ///
-    /// let c = ClientBuilder::new().api_key("the-key")?;
-    /// let p = Probe::list(cl, opts)?;
+    /// let cl = ClientBuilder::new().api_key("the-key").build().unwrap();
+    /// let l = Probe::list(&cl, &Options::from([("country_code", "FR")])).unwrap();
     /// ```
     ///
     #[cfg(feature = "alt-api")]
-    pub fn list(cl: &Client, opts: &HashMap<&str, &str>) -> Result<List<Self>, APIError> {
-        Ok(cl.get_probes(opts)?)
+    pub fn list(cl: &Client, opts: &Options) -> Result<Vec<Self>, APIError> {
+        cl.list::<Probe>(opts.clone())
     }
 }
 
@@ -195,100 +631,297 @@ impl Client {
     ///
     /// ```no_run
     ///  # use atlas_rs::client::ClientBuilder;
-    ///  # use atlas_rs::core::probes::Probe;
     ///
-    ///     let cl = ClientBuilder::new().api_key("foo").verbose(true);
-    ///     let pi = cl.get_probe(666)?;
+    ///     let cl = ClientBuilder::new().api_key("foo").build().unwrap();
+    ///     let pi = cl.get_probe(666).unwrap();
     ///
-    ///     println!("Probe ID {}: {}", 666, pi.description);
+    ///     println!("Probe ID {}: {:?}", 666, pi.description);
     ///  ```
     ///
     #[cfg(feature = "flat-api")]
-    pub fn get_probe(&self, id: u32) -> Result<Probe, APIError> {
-        let opts = &self.opts.clone();
-        let url = format!("{}/probes/{}/", self.endpoint, id);
-        let url = add_opts(&url, opts);
-
-        let resp = self.agent.as_ref().unwrap().get(&url).send();
-
-        let resp = match resp {
-            Ok(resp) => resp,
-            Err(e) => {
-                let aerr = APIError::new(
-                    e.status().unwrap().as_u16(),
-                    "Bad",
-                    e.to_string().as_str(),
-                    "get_probe",
-                );
-                return Err(aerr);
-            }
-        };
-
-        // Try to see if we got an error
-        match resp.status() {
-            StatusCode::OK => {
-                // We could use Response::json() here but it consumes the body.
-                let r = resp.text()?;
-                println!("p={}", r);
-                let p: Probe = serde_json::from_str(&r)?;
-                Ok(p)
-            }
-            _ => {
-                let aerr = resp.json::<APIError>()?;
-                Err(aerr)
-            }
-        }
+    pub fn get_probe(&self, id: impl Into<ProbeId>) -> Result<Probe, APIError> {
+        self.get::<Probe>(id)
     }
 
     /// Get information about a set of probes according to parameters
     ///
     #[cfg(feature = "flat-api")]
-    pub fn get_probes(&self, opts: &HashMap<&str, &str>) -> Result<List<Probe>, APIError> {
-        let gopts = &self.opts.clone();
-        let url = format!("{}/probes/", &self.endpoint);
-
-        // Add global options
-        let url = add_opts(&url, gopts);
-        // Add our specific ones, like page=NN
-        let url = add_opts(&url, opts);
+    pub fn get_probes(&self, opts: &Options) -> Result<Vec<Probe>, APIError> {
+        self.list::<Probe>(opts.clone())
+    }
+}
 
-        let res: List<Probe> = self.fetch_one_page(&url, 1)?;
+/// Bulk operations on probes
+///
+impl Client {
+    /// Apply a set of changes to probes you own, one `PATCH` per probe.  Every probe is
+    /// attempted and the result is reported for each of them.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::load_updates;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let changes = load_updates("probes.csv").unwrap();
+    /// for (id, r) in c.update_probes(&changes) {
+    ///     match r {
+    ///         Ok(_) => println!("{}: ok", id),
+    ///         Err(e) => println!("{}: {}", id, e),
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn update_probes(
+        &self,
+        changes: &[(ProbeId, ProbeUpdate)],
+    ) -> Vec<(ProbeId, Result<Probe, APIError>)> {
+        changes
+            .iter()
+            .map(|(id, u)| (*id, self.probe().update(*id, u)))
+            .collect()
+    }
+}
 
-        if res.count == 0 {
-            return Err(APIError::new(500, "Empty list", "nothing", "get_probes"));
-        }
+impl Entity for Probe {
+    type Id = ProbeId;
 
-        if res.next.is_empty() {
-            // We have no pagination
-        }
-        Ok(res)
-    }
+    const PATH: &'static str = "/probes/";
+    const OPS: &'static [Op] = &[
+        Op::Get,
+        Op::List,
+        Op::Set,
+        Op::Update,
+        Op::Measurement,
+        Op::Archive,
+        Op::Rankings,
+        Op::Tags,
+    ];
 }
 
-impl<T: Display> Routing<T> for Probe {
-    /// Generate the proper URL for the service we want in the given category
+impl Probe {
+    /// Path of the slugs of a given tag, the tag is not an ID so it is escaped
     ///
-    fn set_url(op: Op, p: T) -> String {
-        match op {
-            Op::List => "/probes/".to_string(),      // /list
-            Op::Get => format!("/probes/{}/", p),    // /get
-            Op::Set => format!("/probes/{}/", p),    // /set
-            Op::Update => format!("/probes/{}/", p), // /update
-            Op::Measurement => format!("/probes/{}/measurements/", p), // P/measurements
-            Op::Archive => "/probes/archive/".to_string(), // /archive
-            Op::Rankings => "/probes/rankings/".to_string(), // rankings
-            Op::Tags => "/probes/tags/".to_string(), // /tags/
-            Op::Slugs => format!("/probes/tags/{}/slugs", p), // /tags/T/slugs/
-            _ => panic!("not possible"),
-        }
+    pub(crate) fn slugs_url(tag: &str) -> Result<String, APIError> {
+        Ok(format!(
+            "{}{}/slugs/",
+            Probe::url(Op::Tags, None)?,
+            path_segment(tag)
+        ))
     }
 }
+
 // -------------------------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
+    use tempfile::TempDir;
+
+    use crate::client::ClientBuilder;
+
     use super::*;
 
     #[test]
-    fn test_get_probe() {}
+    fn test_get_probe() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/probes.json"
+            ))
+            .build()
+            .unwrap();
+
+        let p: Probe = c.probe().get(666).unwrap();
+        assert_eq!(Some("FR"), p.country_code.as_deref());
+        assert_eq!(Some(3215), p.asn_v4);
+        assert_eq!("system-ipv4-works", p.tags.unwrap()[0].slug);
+
+        let e = c.probe().get(1).unwrap_err();
+        assert_eq!(404, e.error.status);
+    }
+
+    #[test]
+    fn test_rankings_tags() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/probes.json"
+            ))
+            .build()
+            .unwrap();
+
+        let r = c.probe().rankings().unwrap();
+        assert_eq!(2, r.len());
+        assert_eq!(1234, r[1].id);
+        assert_eq!(Some(1), r[0].rank);
+
+        let cat = TagCatalogue::new(c.probe().tags().unwrap());
+        assert_eq!(2, cat.len());
+        assert_eq!(11000, cat.get("system-ipv4-works").unwrap().total_probes);
+        assert_eq!(Some("user"), cat.get("home").unwrap().ttype.as_deref());
+    }
+
+    fn archived(id: u32, cc: &str, asn: u32, status: &str) -> ArchivedProbe {
+        let txt = format!(
+            r#"{{"id":{},"country_code":"{}","asn_v4":{},"status":{{"id":1,"name":"{}","since":"2022-05-01T00:00:00Z"}}}}"#,
+            id, cc, asn, status
+        );
+        serde_json::from_str(&txt).unwrap()
+    }
+
+    #[test]
+    fn test_archive_diff() {
+        let old = vec![
+            archived(1, "FR", 3215, "Connected"),
+            archived(2, "NL", 3333, "Connected"),
+            archived(3, "DE", 3320, "Connected"),
+        ];
+        let new = vec![
+            archived(4, "BE", 5432, "Connected"),
+            archived(3, "AT", 3320, "Disconnected"),
+            archived(1, "FR", 12322, "Connected"),
+        ];
+
+        let d = ArchiveDiff::new(&old, &new);
+
+        assert_eq!(vec![4], d.appeared);
+        assert_eq!(vec![2], d.disappeared);
+        assert_eq!(
+            vec![Changed {
+                id: ProbeId(3),
+                before: Some("DE".to_string()),
+                after: Some("AT".to_string())
+            }],
+            d.country
+        );
+        assert_eq!(
+            vec![Changed {
+                id: ProbeId(1),
+                before: Some(3215),
+                after: Some(12322)
+            }],
+            d.asn_v4
+        );
+        assert!(d.asn_v6.is_empty());
+        assert_eq!(1, d.status.len());
+        assert_eq!(3, d.status[0].id);
+    }
+
+    #[test]
+    fn test_slugs_url() {
+        assert_eq!(
+            "/probes/tags/system-ipv4-works/slugs/",
+            Probe::slugs_url("system-ipv4-works").unwrap()
+        );
+        assert_eq!(
+            "/probes/tags/a%20b%2Fc%3F/slugs/",
+            Probe::slugs_url("a b/c?").unwrap()
+        );
+    }
+
+    fn catalogue() -> TagCatalogue {
+        let txt = r#"[
+            {"name": "system: IPv4 Works", "slug": "system-ipv4-works", "type": "system", "total_probes": 1000},
+            {"name": "system: IPv6 Works", "slug": "system-ipv6-works", "type": "system", "total_probes": 500},
+            {"name": "NAT", "slug": "nat", "type": "user", "total_probes": 800}
+        ]"#;
+        TagCatalogue::new(serde_json::from_str(txt).unwrap())
+    }
+
+    #[test]
+    fn test_tags_complete() {
+        let cat = catalogue();
+
+        let v: Vec<&str> = cat
+            .complete("system")
+            .iter()
+            .map(|t| t.slug.as_str())
+            .collect();
+        assert_eq!(vec!["system-ipv4-works", "system-ipv6-works"], v);
+        assert_eq!(1, cat.complete("Na").len());
+        assert!(cat.complete("cable").is_empty());
+    }
+
+    #[test]
+    fn test_tags_unknown() {
+        let cat = catalogue();
+
+        assert!(cat.unknown("+system-ipv4-works !nat").is_empty());
+        assert_eq!(vec!["cable"], cat.unknown("nat -cable"));
+    }
+
+    #[test]
+    fn test_probe_update_serialize() {
+        let u = ProbeUpdate::new().is_public(false).location(48.85, 2.35);
+
+        assert_eq!(
+            r#"{"is_public":false,"geometry":{"type":"Point","coordinates":[2.35,48.85]}}"#,
+            serde_json::to_string(&u).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_load_updates_csv() {
+        let dir = TempDir::new().unwrap();
+        let fname = dir.path().join("updates.csv");
+        fs::write(
+            &fname,
+            "id,description,tags,is_public,latitude,longitude\n\
+             666,\"Home, sweet home\",home ftth,true,,\n\
+             667,,,,48.85,2.35\n",
+        )
+        .unwrap();
+
+        let u = load_updates(&fname).unwrap();
+
+        assert_eq!(2, u.len());
+        assert_eq!(
+            (
+                ProbeId(666),
+                ProbeUpdate::new()
+                    .description("Home, sweet home")
+                    .tags(&["home", "ftth"])
+                    .is_public(true)
+            ),
+            u[0]
+        );
+        assert_eq!(
+            (ProbeId(667), ProbeUpdate::new().location(48.85, 2.35)),
+            u[1]
+        );
+    }
+
+    #[test]
+    fn test_load_updates_toml() {
+        let dir = TempDir::new().unwrap();
+        let fname = dir.path().join("updates.toml");
+        fs::write(
+            &fname,
+            r#"
+            [[probe]]
+            id = 666
+            tags = ["home"]
+
+            [[probe]]
+            id = 667
+            latitude = 48.85
+            "#,
+        )
+        .unwrap();
+
+        let u = load_updates(&fname);
+
+        // Second probe has no longitude
+        assert!(u.is_err());
+    }
+
+    #[test]
+    fn test_archive_diff_same() {
+        let old = vec![archived(1, "FR", 3215, "Connected")];
+
+        assert!(ArchiveDiff::new(&old, &old).is_empty());
+    }
 }
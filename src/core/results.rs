@@ -0,0 +1,561 @@
+//! Structs to deal with measurement results
+//!
+//! Every measurement type has its own result format, they all share a common set of fields
+//! (see `ResultHeader`) and the `type` field is used to select the right one.  Fields are
+//! mostly optional as they vary with the firmware version of the probe.
+//!
+//! See the [result format] documentation for the details.
+//!
+//! [result format]: https://atlas.ripe.net/docs/apis/result-format/
+
+// We have the following call tree:
+//
+// ----- /measurements ----- P ----- /results
+//                             ----- /latest
+//                             ----- /status-check
+
+// -------------------------------------------------------------------------
+// Standard library
+use std::collections::BTreeMap;
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Our crates
+use crate::entity::json_display;
+use crate::ids::{MeasurementId, ProbeId};
+use crate::kinds::MeasurementType;
+use crate::option::Options;
+use crate::timestamp::Timestamp;
+
+// -------------------------------------------------------------------------
+
+/// Fields common to all results
+///
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ResultHeader {
+    /// Firmware version of the probe
+    pub fw: u32,
+    /// Last time synchronised (seconds ago)
+    pub lts: Option<i64>,
+    /// Inet family, 4 or 6
+    pub af: Option<u32>,
+    /// IP address of the target
+    pub dst_addr: Option<String>,
+    /// Name of the target
+    pub dst_name: Option<String>,
+    /// Source address used by the probe
+    pub src_addr: Option<String>,
+    /// Public IP address of the probe
+    pub from: Option<String>,
+    /// Measurement ID
+    pub msm_id: MeasurementId,
+    /// Measurement name
+    pub msm_name: Option<String>,
+    /// Probe ID
+    pub prb_id: ProbeId,
+    /// POSIX time of the result
+    pub timestamp: Timestamp,
+    /// POSIX time the result was stored
+    pub stored_timestamp: Option<Timestamp>,
+    /// Group ID for grouped measurements
+    pub group_id: Option<MeasurementId>,
+}
+
+// -------------------------------------------------------------------------
+
+/// Reply to one ping packet
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum PingReply {
+    /// Round-trip time in ms
+    Rtt {
+        rtt: f64,
+        /// Source address if different from `src_addr`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        src_addr: Option<String>,
+        /// TTL of the reply
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ttl: Option<u32>,
+    },
+    /// Timeout (`"*"`)
+    Timeout { x: String },
+    /// Error
+    Error { error: String },
+}
+
+/// Result of a ping measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PingResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// Average RTT (-1 if all lost)
+    pub avg: Option<f64>,
+    /// Minimal RTT
+    pub min: Option<f64>,
+    /// Maximal RTT
+    pub max: Option<f64>,
+    /// Packets sent
+    pub sent: Option<u32>,
+    /// Packets received
+    pub rcvd: Option<u32>,
+    /// Duplicate packets
+    pub dup: Option<u32>,
+    /// Packet size
+    pub size: Option<u32>,
+    /// Replies
+    #[serde(default)]
+    pub result: Vec<PingReply>,
+}
+
+/// Reply to one traceroute packet
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum HopReply {
+    /// Reply from a router
+    Reply {
+        from: String,
+        rtt: Option<f64>,
+        size: Option<u32>,
+        ttl: Option<u32>,
+    },
+    /// Timeout (`"*"`)
+    Timeout { x: String },
+    /// Error
+    Error { err: serde_json::Value },
+}
+
+/// One hop of a traceroute
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Hop {
+    /// Hop number
+    pub hop: u32,
+    /// Error for this hop
+    pub error: Option<String>,
+    /// Replies for this hop
+    #[serde(default)]
+    pub result: Vec<HopReply>,
+}
+
+/// Result of a traceroute measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TracerouteResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// POSIX end time
+    pub endtime: Option<i64>,
+    /// Variation for the Paris mode
+    pub paris_id: Option<u32>,
+    /// Protocol: ICMP, UDP or TCP
+    pub proto: Option<String>,
+    /// Packet size
+    pub size: Option<u32>,
+    /// Hops
+    #[serde(default)]
+    pub result: Vec<Hop>,
+}
+
+/// DNS answer
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DnsAnswer {
+    /// Answer count
+    #[serde(rename = "ANCOUNT")]
+    pub ancount: Option<u32>,
+    /// Additional records count
+    #[serde(rename = "ARCOUNT")]
+    pub arcount: Option<u32>,
+    /// Query ID
+    #[serde(rename = "ID")]
+    pub id: Option<u32>,
+    /// Name server count
+    #[serde(rename = "NSCOUNT")]
+    pub nscount: Option<u32>,
+    /// Query count
+    #[serde(rename = "QDCOUNT")]
+    pub qdcount: Option<u32>,
+    /// Base64-encoded answer
+    pub abuf: Option<String>,
+    /// Response time in ms
+    pub rt: Option<f64>,
+    /// Answer size
+    pub size: Option<u32>,
+}
+
+/// One answer in a set when the probe has several resolvers
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DnsSet {
+    /// Inet family
+    pub af: Option<u32>,
+    /// Resolver address
+    pub dst_addr: Option<String>,
+    /// Answer
+    pub result: Option<DnsAnswer>,
+    /// Error
+    pub error: Option<serde_json::Value>,
+}
+
+/// Result of a DNS measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DnsResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// Protocol: UDP or TCP
+    pub proto: Option<String>,
+    /// Answer when querying a single server
+    pub result: Option<DnsAnswer>,
+    /// Answers when using the probe resolvers
+    pub resultset: Option<Vec<DnsSet>>,
+    /// Error
+    pub error: Option<serde_json::Value>,
+}
+
+/// Result of an SSL certificate measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SslCertResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// Target port
+    pub dst_port: Option<String>,
+    /// Certificate chain (PEM)
+    #[serde(default)]
+    pub cert: Vec<String>,
+    /// Protocol: SSL or TLS
+    pub method: Option<String>,
+    /// Protocol version
+    pub ver: Option<String>,
+    /// Response time in ms
+    pub rt: Option<f64>,
+    /// Time to connect in ms
+    pub ttc: Option<f64>,
+    /// TLS alert
+    pub alert: Option<serde_json::Value>,
+    /// Error
+    pub err: Option<String>,
+}
+
+/// One HTTP request
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HttpReply {
+    /// Inet family
+    pub af: Option<u32>,
+    /// Body size
+    pub bsize: Option<u32>,
+    /// Header size
+    pub hsize: Option<u32>,
+    /// Target address
+    pub dst_addr: Option<String>,
+    /// HTTP method
+    pub method: Option<String>,
+    /// HTTP status code
+    pub res: Option<u32>,
+    /// Response time in ms
+    pub rt: Option<f64>,
+    /// Source address
+    pub src_addr: Option<String>,
+    /// HTTP version
+    pub ver: Option<String>,
+    /// Error
+    pub err: Option<String>,
+}
+
+/// Result of an HTTP measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HttpResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// Requested URI
+    pub uri: Option<String>,
+    /// Requests
+    #[serde(default)]
+    pub result: Vec<HttpReply>,
+}
+
+/// Reply to one NTP packet
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum NtpReply {
+    /// Timing information
+    Reply {
+        #[serde(rename = "final-ts")]
+        final_ts: f64,
+        offset: f64,
+        #[serde(rename = "origin-ts")]
+        origin_ts: f64,
+        #[serde(rename = "receive-ts")]
+        receive_ts: f64,
+        rtt: f64,
+        #[serde(rename = "transmit-ts")]
+        transmit_ts: f64,
+    },
+    /// Timeout (`"*"`)
+    Timeout { x: String },
+}
+
+/// Result of an NTP measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct NtpResult {
+    #[serde(flatten)]
+    pub header: ResultHeader,
+    /// Leap indicator
+    pub li: Option<String>,
+    /// Server mode
+    pub mode: Option<String>,
+    /// Poll interval
+    pub poll: Option<f64>,
+    /// Server precision
+    pub precision: Option<f64>,
+    /// Reference ID
+    #[serde(rename = "ref-id")]
+    pub ref_id: Option<String>,
+    /// Server stratum
+    pub stratum: Option<u32>,
+    /// NTP version
+    pub version: Option<u32>,
+    /// Replies
+    #[serde(default)]
+    pub result: Vec<NtpReply>,
+}
+
+// -------------------------------------------------------------------------
+
+/// Any measurement result, selected by its `type`
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MeasurementResult {
+    Ping(PingResult),
+    Traceroute(TracerouteResult),
+    Dns(DnsResult),
+    Sslcert(SslCertResult),
+    Http(HttpResult),
+    Ntp(NtpResult),
+}
+
+impl MeasurementResult {
+    /// Common fields
+    ///
+    pub fn header(&self) -> &ResultHeader {
+        match self {
+            MeasurementResult::Ping(r) => &r.header,
+            MeasurementResult::Traceroute(r) => &r.header,
+            MeasurementResult::Dns(r) => &r.header,
+            MeasurementResult::Sslcert(r) => &r.header,
+            MeasurementResult::Http(r) => &r.header,
+            MeasurementResult::Ntp(r) => &r.header,
+        }
+    }
+
+    /// Probe ID
+    ///
+    pub fn prb_id(&self) -> ProbeId {
+        self.header().prb_id
+    }
+
+    /// Measurement ID
+    ///
+    pub fn msm_id(&self) -> MeasurementId {
+        self.header().msm_id
+    }
+
+    /// POSIX time of the result
+    ///
+    pub fn timestamp(&self) -> Timestamp {
+        self.header().timestamp
+    }
+
+    /// Type of the result
+    ///
+    pub fn mtype(&self) -> MeasurementType {
+        match self {
+            MeasurementResult::Ping(_) => MeasurementType::Ping,
+            MeasurementResult::Traceroute(_) => MeasurementType::Traceroute,
+            MeasurementResult::Dns(_) => MeasurementType::Dns,
+            MeasurementResult::Sslcert(_) => MeasurementType::SslCert,
+            MeasurementResult::Http(_) => MeasurementType::Http,
+            MeasurementResult::Ntp(_) => MeasurementType::Ntp,
+        }
+    }
+}
+
+json_display!(MeasurementResult);
+
+// -------------------------------------------------------------------------
+
+/// Status of one probe in a status check
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ProbeCheck {
+    /// Is this probe alerting?
+    pub alert: bool,
+    /// Last RTT, `None` if all packets were lost
+    pub last: Option<f64>,
+    /// Packet loss of the last result in %
+    pub last_packet_loss: Option<f64>,
+    /// Why the probe was selected (country, ASN, etc.)
+    pub source: Option<String>,
+    /// All RTTs within the lookback window, with `show_all`
+    pub all: Option<Vec<Option<f64>>>,
+}
+
+/// Result of the status check of a ping measurement
+///
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StatusCheck {
+    /// Is the whole measurement alerting?
+    pub global_alert: bool,
+    /// How many probes are alerting
+    pub total_alerts: u32,
+    /// Status per probe ID
+    #[serde(default)]
+    pub probes: BTreeMap<String, ProbeCheck>,
+}
+
+impl StatusCheck {
+    /// IDs of the alerting probes
+    ///
+    pub fn alerting(&self) -> Vec<ProbeId> {
+        self.probes
+            .iter()
+            .filter(|(_, p)| p.alert)
+            .filter_map(|(id, _)| id.parse().ok())
+            .collect()
+    }
+}
+
+json_display!(StatusCheck);
+
+/// Thresholds for the status check, to be given to `with()`.
+///
+#[derive(Clone, Debug, Default)]
+pub struct StatusCheckFilter(Options);
+
+impl StatusCheckFilter {
+    /// Use the API defaults
+    ///
+    pub fn new() -> Self {
+        StatusCheckFilter(Options::new())
+    }
+
+    /// Maximum packet loss in % before a probe alerts
+    ///
+    pub fn max_packet_loss(mut self, pct: u32) -> Self {
+        self.0["max_packet_loss"] = pct.to_string();
+        self
+    }
+
+    /// Number of alerting probes before the whole measurement alerts
+    ///
+    pub fn permitted_total_alerts(mut self, n: u32) -> Self {
+        self.0["permitted_total_alerts"] = n.to_string();
+        self
+    }
+
+    /// Number of past results used to compute the median RTT
+    ///
+    pub fn lookback(mut self, n: u32) -> Self {
+        self.0["lookback"] = n.to_string();
+        self
+    }
+
+    /// Increase in % of the RTT over the median before a probe alerts
+    ///
+    pub fn median_rtt_threshold(mut self, pct: u32) -> Self {
+        self.0["median_rtt_threshold"] = pct.to_string();
+        self
+    }
+
+    /// Return all the RTTs within the lookback window
+    ///
+    pub fn show_all(mut self) -> Self {
+        self.0["show_all"] = "true".to_string();
+        self
+    }
+}
+
+impl From<StatusCheckFilter> for Options {
+    fn from(f: StatusCheckFilter) -> Self {
+        f.0
+    }
+}
+
+// -------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping() {
+        let txt = r#"{"fw":4790,"lts":19,"dst_name":"193.0.14.129","af":4,
+            "dst_addr":"193.0.14.129","src_addr":"192.168.1.2","proto":"ICMP","ttl":55,
+            "size":48,"result":[{"rtt":9.41},{"x":"*"},{"rtt":9.3}],"dup":0,"rcvd":2,
+            "sent":3,"min":9.3,"max":9.41,"avg":9.355,"msm_id":1001,"prb_id":666,
+            "timestamp":1652000000,"msm_name":"Ping","from":"1.2.3.4","type":"ping",
+            "group_id":1001,"step":240,"stored_timestamp":1652000010}"#;
+        let r: MeasurementResult = serde_json::from_str(txt).unwrap();
+
+        assert_eq!(MeasurementType::Ping, r.mtype());
+        assert_eq!(666, r.prb_id());
+        assert_eq!(1001, r.msm_id());
+        match r {
+            MeasurementResult::Ping(p) => {
+                assert_eq!(Some(2), p.rcvd);
+                assert_eq!(3, p.result.len());
+                assert!(matches!(p.result[1], PingReply::Timeout { .. }));
+            }
+            _ => panic!("not a ping"),
+        }
+    }
+
+    #[test]
+    fn test_traceroute() {
+        let txt = r#"{"fw":4790,"af":4,"dst_addr":"193.0.14.129","msm_id":5001,
+            "prb_id":1,"timestamp":1652000000,"type":"traceroute","proto":"UDP",
+            "paris_id":1,"endtime":1652000005,"result":[
+                {"hop":1,"result":[{"from":"192.168.1.1","rtt":1.2,"size":28,"ttl":64},{"x":"*"}]},
+                {"hop":255,"result":[{"x":"*"}]}
+            ]}"#;
+        let r: MeasurementResult = serde_json::from_str(txt).unwrap();
+
+        match r {
+            MeasurementResult::Traceroute(t) => {
+                assert_eq!(2, t.result.len());
+                assert!(matches!(t.result[0].result[0], HopReply::Reply { .. }));
+            }
+            _ => panic!("not a traceroute"),
+        }
+    }
+
+    #[test]
+    fn test_status_check() {
+        let txt = r#"{"global_alert":false,"total_alerts":1,"probes":{
+            "666":{"alert":true,"last":null,"last_packet_loss":100.0,"source":"Country: FR"},
+            "1234":{"alert":false,"last":12.5,"last_packet_loss":0.0,"source":"Area: WW",
+                    "all":[12.1,null,12.5]}
+        }}"#;
+        let s: StatusCheck = serde_json::from_str(txt).unwrap();
+
+        assert_eq!(vec![666], s.alerting());
+        assert_eq!(Some(12.5), s.probes["1234"].last);
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let txt = r#"{"fw":4790,"msm_id":1,"prb_id":1,"timestamp":0,"type":"wifi"}"#;
+
+        assert!(serde_json::from_str::<MeasurementResult>(txt).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Module defining the `Entity` trait shared by the main API objects.
+//!
+//! An entity is an object living under its own path in the API (`/probes/`, `/anchors/`, etc.),
+//! identified by an ID of a given type and listed in pages.  Implementing `Entity` for a type
+//! is all that is needed to get it with `Client::get()` and list it with `Client::list()`:
+//!
+//! ```no_run
+//! use atlas_rs::client::ClientBuilder;
+//! use atlas_rs::core::anchors::{Anchor, AnchorFilter};
+//! use atlas_rs::core::probes::Probe;
+//!
+//! let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+//!
+//! let p = c.get::<Probe>(666).unwrap();
+//! let l = c.list::<Anchor>(AnchorFilter::new().country("FR")).unwrap();
+//! ```
+//!
+//! The same paths are used by `RequestBuilder<E>` (what `c.probe()` returns), which also has
+//! the calls specific to each object like `c.probe().rankings()`.
+//!
+
+// Standard library
+use std::fmt::Display;
+use std::str::FromStr;
+
+// External crates
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Our crates
+use crate::client::Client;
+use crate::common::{decode_response, Pages};
+use crate::errors::APIError;
+use crate::option::Options;
+use crate::request::Op;
+use crate::transport::Method;
+
+// ---------------------------------------------------------------------------
+
+/// An API object with its own path and ID.
+///
+/// Example:
+/// ```
+/// use atlas_rs::core::probes::Probe;
+/// use atlas_rs::entity::Entity;
+/// use atlas_rs::ids::ProbeId;
+/// use atlas_rs::request::Op;
+///
+/// assert_eq!("/probes/666/", Probe::url(Op::Get, Some(&ProbeId(666))).unwrap());
+/// assert!(Probe::url(Op::Delete, Some(&ProbeId(666))).is_err());
+/// ```
+///
+pub trait Entity: DeserializeOwned + Serialize {
+    /// Type of the ID, also parsed from the URL of links to this object
+    type Id: Display + FromStr;
+
+    /// Base path, like `/probes/`
+    const PATH: &'static str;
+
+    /// Operations supported by the API for this object
+    const OPS: &'static [Op];
+
+    /// Path for the given operation, with the ID for the ones on a single object
+    ///
+    fn url(op: Op, id: Option<&Self::Id>) -> Result<String, APIError> {
+        if !Self::OPS.contains(&op) {
+            return Err(bad_op(op, Self::PATH));
+        }
+        let sub = op.segment().map(|s| format!("{}/", s)).unwrap_or_default();
+        match (op, id) {
+            (Op::List | Op::Create | Op::Info, _) => Ok(Self::PATH.to_string()),
+            // A single object or one of its sub-lists like `/measurements/1001/results/`
+            (
+                Op::Get
+                | Op::Set
+                | Op::Update
+                | Op::Delete
+                | Op::Measurement
+                | Op::Results
+                | Op::Latest
+                | Op::StatusCheck,
+                Some(id),
+            ) => Ok(format!("{}{}/{}", Self::PATH, id, sub)),
+            // Calls on the whole category like `/probes/rankings/`
+            (
+                Op::Archive
+                | Op::Claim
+                | Op::Expenses
+                | Op::Incomes
+                | Op::Members
+                | Op::Permissions
+                | Op::Rankings
+                | Op::Tags
+                | Op::Transactions
+                | Op::Transfers,
+                None,
+            ) => Ok(format!("{}{}", Self::PATH, sub)),
+            (op, _) => Err(bad_op(op, Self::PATH)),
+        }
+    }
+}
+
+/// Defines `Display` as the JSON form of the object, for all the given types
+///
+macro_rules! json_display {
+    ($($name:ty),+ $(,)?) => {
+        $(
+            impl std::fmt::Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}", serde_json::to_string(self).unwrap())
+                }
+            }
+        )+
+    };
+}
+
+pub(crate) use json_display;
+
+// ---------------------------------------------------------------------------
+
+/// Generic calls for all entities
+///
+impl Client {
+    /// Get one object by its ID
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::measurements::Measurement;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let m = c.get::<Measurement>(1001).unwrap();
+    /// ```
+    ///
+    pub fn get<T: Entity>(&self, id: impl Into<T::Id>) -> Result<T, APIError> {
+        let id = id.into();
+        let url = self.entity_url(&T::url(Op::Get, Some(&id))?, &self.opts)?;
+
+        decode_response(self.send(self.http_request(Method::Get, url))?)
+    }
+
+    /// List all the objects matching the filter, fetching all pages
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::Probe;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let l = c.list::<Probe>([("country_code", "FR")]).unwrap();
+    /// ```
+    ///
+    pub fn list<T: Entity>(&self, filter: impl Into<Options>) -> Result<Vec<T>, APIError> {
+        let mut opts = self.opts.clone();
+        opts.merge(&filter.into());
+        let url = self.entity_url(&T::url(Op::List, None)?, &opts)?;
+
+        Pages::new(self, url.to_string()).collect()
+    }
+
+    /// Full URL for the given path and options
+    ///
+    fn entity_url(&self, path: &str, opts: &Options) -> Result<reqwest::Url, APIError> {
+        reqwest::Url::parse_with_params(&format!("{}{}", self.endpoint.as_str(), path), opts)
+            .map_err(|e| APIError::new(400, "Bad URL", &e.to_string(), "entity_url"))
+    }
+}
+
+/// Error for an operation the API does not have for this entity
+///
+fn bad_op(op: Op, path: &str) -> APIError {
+    APIError::new(
+        400,
+        "Bad operation",
+        &format!("{:?} is not available on {}", op, path),
+        "entity",
+    )
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::client::ClientBuilder;
+    use crate::core::keys::Key;
+    use crate::core::probes::Probe;
+    use crate::ids::{KeyUuid, ProbeId};
+
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        assert_eq!("/probes/", Probe::url(Op::List, None).unwrap());
+        assert_eq!(
+            "/keys/0123-abcd/",
+            Key::url(Op::Delete, Some(&KeyUuid("0123-abcd".to_string()))).unwrap()
+        );
+
+        let e = Probe::url(Op::Get, None).unwrap_err();
+        assert_eq!("Bad operation", e.error.title);
+        assert!(Probe::url(Op::Create, Some(&ProbeId(1))).is_err());
+
+        assert_eq!(
+            "/probes/666/measurements/",
+            Probe::url(Op::Measurement, Some(&ProbeId(666))).unwrap()
+        );
+        assert_eq!("/probes/tags/", Probe::url(Op::Tags, None).unwrap());
+        // Sub-lists need the object and calls on the category must not have one
+        assert!(Probe::url(Op::Measurement, None).is_err());
+        assert!(Probe::url(Op::Rankings, Some(&ProbeId(1))).is_err());
+    }
+
+    #[test]
+    fn test_get() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/probes.json"
+            ))
+            .build()
+            .unwrap();
+
+        let p = c.get::<Probe>(ProbeId(666)).unwrap();
+        assert_eq!(ProbeId(666), p.id);
+        assert_eq!(Some("FR"), p.country_code.as_deref());
+    }
+}
@@ -109,6 +109,27 @@ impl From<reqwest::Error> for APIError {
     }
 }
 
+/// Convert a decode error from `csv`
+impl From<csv::Error> for APIError {
+    fn from(error: csv::Error) -> Self {
+        APIError::new(500, "csv/decode", &error.to_string(), "csv")
+    }
+}
+
+/// Convert a decode error from `toml`
+impl From<toml::de::Error> for APIError {
+    fn from(error: toml::de::Error) -> Self {
+        APIError::new(500, "toml/decode", &error.to_string(), "toml")
+    }
+}
+
+/// Convert a connection error from `tungstenite`
+impl From<tungstenite::Error> for APIError {
+    fn from(error: tungstenite::Error) -> Self {
+        APIError::new(500, "websocket", &error.to_string(), "tungstenite")
+    }
+}
+
 /// Convert our APIError into an anyhow one
 impl From<APIError> for anyhow::Error {
     fn from(aerr: APIError) -> Self {
@@ -0,0 +1,333 @@
+//! Module defining the identifiers of the main API objects.
+//!
+//! Probes, measurements, anchors and participation requests are all numbered, and keys have
+//! an UUID.  Having a distinct type for each means a measurement ID can not be given where a
+//! probe is expected.  They are serialized as the bare number (or string) so the JSON from the API is unchanged.
+//!
+//! Conversions from `Param` (and from strings) check the value and return an error instead
+//! of defaulting to `0`.
+//!
+//! Example:
+//! ```
+//! use atlas_rs::ids::{MeasurementId, ProbeId};
+//! use atlas_rs::param::Param;
+//!
+//! let p = ProbeId::from(666);
+//! let m: MeasurementId = "1001".parse().unwrap();
+//!
+//! assert_eq!("666", p.to_string());
+//! assert_eq!(1001, u32::from(m));
+//! assert!(ProbeId::try_from(Param::from("foo")).is_err());
+//! ```
+//!
+//! Calls on an object only take its own ID type:
+//! ```compile_fail
+//! # use atlas_rs::client::ClientBuilder;
+//! # use atlas_rs::ids::MeasurementId;
+//! let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+//!
+//! let p = c.probe().get(MeasurementId(1001));
+//! ```
+//!
+
+// Standard library
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Our crates
+use crate::errors::APIError;
+use crate::param::Param;
+
+// ---------------------------------------------------------------------------
+
+/// Defines a numeric identifier, all share the same behaviour.
+///
+macro_rules! numeric_id {
+    ($(#[$doc:meta])* $name:ident, $what:literal) => {
+        $(#[$doc])*
+        #[derive(
+            Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub u32);
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                Display::fmt(&self.0, f)
+            }
+        }
+
+        impl From<u32> for $name {
+            fn from(v: u32) -> Self {
+                $name(v)
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(v: $name) -> Self {
+                v.0
+            }
+        }
+
+        impl PartialEq<u32> for $name {
+            fn eq(&self, other: &u32) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl PartialEq<$name> for u32 {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.0
+            }
+        }
+
+        impl From<$name> for Param {
+            fn from(v: $name) -> Self {
+                Param::U(v.0)
+            }
+        }
+
+        /// Only strictly positive numbers are valid
+        ///
+        impl TryFrom<Param> for $name {
+            type Error = APIError;
+
+            fn try_from(p: Param) -> Result<Self, Self::Error> {
+                let v = match &p {
+                    Param::U(v) => Some(*v),
+                    Param::I(v) => u32::try_from(*v).ok(),
+                    Param::L(v) => u32::try_from(*v).ok(),
+                    Param::S(s) => s.trim().parse::<u32>().ok(),
+                };
+                match v {
+                    Some(v) if v > 0 => Ok($name(v)),
+                    _ => Err(bad_id(&p, $what, stringify!($name))),
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = APIError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $name::try_from(Param::from(s))
+            }
+        }
+    };
+}
+
+numeric_id!(
+    /// ID of a probe
+    ///
+    ProbeId,
+    "probe ID"
+);
+
+numeric_id!(
+    /// ID of a measurement
+    ///
+    MeasurementId,
+    "measurement ID"
+);
+
+numeric_id!(
+    /// ID of an anchor
+    ///
+    AnchorId,
+    "anchor ID"
+);
+
+numeric_id!(
+    /// ID of an anchor measurement
+    ///
+    AnchorMeasurementId,
+    "anchor measurement ID"
+);
+
+numeric_id!(
+    /// ID of a participation request
+    ///
+    ParticipationRequestId,
+    "participation request ID"
+);
+
+// ---------------------------------------------------------------------------
+
+/// UUID of an API key
+///
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeyUuid(pub String);
+
+impl Display for KeyUuid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
+/// No check, use `parse()` to validate a UUID given by the user
+///
+impl From<&str> for KeyUuid {
+    fn from(s: &str) -> Self {
+        KeyUuid(s.to_string())
+    }
+}
+
+impl From<String> for KeyUuid {
+    fn from(s: String) -> Self {
+        KeyUuid(s)
+    }
+}
+
+impl From<KeyUuid> for String {
+    fn from(v: KeyUuid) -> Self {
+        v.0
+    }
+}
+
+impl From<KeyUuid> for Param {
+    fn from(v: KeyUuid) -> Self {
+        Param::S(v.0)
+    }
+}
+
+impl From<&KeyUuid> for Param {
+    fn from(v: &KeyUuid) -> Self {
+        Param::S(v.0.clone())
+    }
+}
+
+/// Only hexadecimal digits and dashes are valid
+///
+impl TryFrom<Param> for KeyUuid {
+    type Error = APIError;
+
+    fn try_from(p: Param) -> Result<Self, Self::Error> {
+        match &p {
+            Param::S(s)
+                if !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit() || c == '-') =>
+            {
+                Ok(KeyUuid(s.to_string()))
+            }
+            _ => Err(bad_id(&p, "key UUID", "KeyUuid")),
+        }
+    }
+}
+
+impl FromStr for KeyUuid {
+    type Err = APIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyUuid::try_from(Param::from(s))
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// ID of objects which have none, like the credits of an account.  It can not be created so
+/// calls needing an ID do not compile.
+///
+/// ```compile_fail
+/// # use atlas_rs::client::ClientBuilder;
+/// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+///
+/// let _ = c.credits().get(1);
+/// ```
+///
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum NoId {}
+
+impl Display for NoId {
+    fn fmt(&self, _f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
+/// Nothing is ever valid
+///
+impl FromStr for NoId {
+    type Err = APIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Err(bad_id(&Param::from(s), "ID, there is none", "NoId"))
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Error for an invalid identifier
+///
+fn bad_id(p: &Param, what: &str, name: &str) -> APIError {
+    APIError::new(
+        400,
+        "Bad ID",
+        &format!("'{}' is not a valid {}", p, what),
+        name,
+    )
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(Param::U(666), 666)]
+    #[case(Param::I(666), 666)]
+    #[case(Param::L(666), 666)]
+    #[case(Param::from("666"), 666)]
+    fn test_probe_id_ok(#[case] p: Param, #[case] id: u32) {
+        assert_eq!(ProbeId(id), ProbeId::try_from(p).unwrap());
+    }
+
+    #[rstest]
+    #[case(Param::U(0))]
+    #[case(Param::I(-1))]
+    #[case(Param::L(1 << 40))]
+    #[case(Param::from("foo"))]
+    #[case(Param::from(""))]
+    fn test_probe_id_nok(#[case] p: Param) {
+        let e = ProbeId::try_from(p).unwrap_err();
+        assert_eq!(400, e.error.status);
+        assert_eq!("Bad ID", e.error.title);
+    }
+
+    #[test]
+    fn test_ids_serde() {
+        let m: MeasurementId = serde_json::from_str("1001").unwrap();
+        assert_eq!(MeasurementId(1001), m);
+        assert_eq!("1001", serde_json::to_string(&m).unwrap());
+
+        let k: KeyUuid = serde_json::from_str(r#""abcd-1234""#).unwrap();
+        assert_eq!(r#""abcd-1234""#, serde_json::to_string(&k).unwrap());
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!("       666", format!("{:>10}", ProbeId(666)));
+        assert_eq!("abcd  ", format!("{:<6}", KeyUuid("abcd".to_string())));
+    }
+
+    #[test]
+    fn test_key_uuid() {
+        let k: KeyUuid = "d1e1a2b3-0123-4567-89ab-cdef01234567".parse().unwrap();
+        assert_eq!("d1e1a2b3-0123-4567-89ab-cdef01234567", k.to_string());
+
+        assert!("not a key".parse::<KeyUuid>().is_err());
+        assert!(KeyUuid::try_from(Param::U(1)).is_err());
+        assert_eq!(KeyUuid::from("abcd"), KeyUuid::from("abcd".to_string()));
+    }
+
+    #[test]
+    fn test_no_id() {
+        assert!("".parse::<NoId>().is_err());
+        assert!("1".parse::<NoId>().is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! Module defining the enumerated values found in the API objects.
+//!
+//! Probe and measurement statuses, measurement types, etc. are all strings in the JSON
+//! sent by the API.  They are decoded into enums so they can be matched on, the name
+//! comparison ignoring case, spaces and dashes (`Never Connected`, `never-connected`).
+//! As RIPE may add new values at any time, anything we do not know about ends up in the
+//! `Unknown` variant instead of failing the whole decoding, and is sent back unchanged.
+//!
+//! Example:
+//! ```
+//! use atlas_rs::kinds::{MeasurementState, MeasurementType};
+//!
+//! let t: MeasurementType = "ping".parse().unwrap();
+//! let s = MeasurementState::from("Forced-stop");
+//!
+//! assert_eq!(MeasurementType::Ping, t);
+//! assert_eq!("Forced to stop", s.to_string());
+//! assert_eq!(MeasurementType::Unknown("wifi".to_string()), "wifi".parse::<MeasurementType>().unwrap());
+//! ```
+//!
+
+// Standard library
+use std::convert::Infallible;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// External crates
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// ---------------------------------------------------------------------------
+
+/// Defines an enum mapped to the strings used by the API, all share the same behaviour.
+/// The first string of each variant is the one sent to the API, the others are accepted
+/// when decoding.
+///
+macro_rules! api_enum {
+    ($(#[$doc:meta])* $name:ident { $($(#[$vdoc:meta])* $var:ident => $api:literal $(| $alias:literal)*),+ $(,)? }) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+        pub enum $name {
+            $($(#[$vdoc])* $var,)+
+            /// Value not known (yet) by this crate
+            Unknown(String),
+        }
+
+        impl $name {
+            /// Name as used by the API
+            ///
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$var => $api,)+
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.pad(self.as_str())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                let n = normalise(s);
+                $(
+                    if n == normalise($api) $(|| n == normalise($alias))* {
+                        return $name::$var;
+                    }
+                )+
+                $name::Unknown(s.to_string())
+            }
+        }
+
+        /// Never fails, unknown values are kept as-is
+        ///
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok($name::from(s))
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                let other = $name::from(*other);
+                *self == other
+            }
+        }
+
+        impl PartialEq<$name> for &str {
+            fn eq(&self, other: &$name) -> bool {
+                let me = $name::from(*self);
+                me == *other
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                Ok($name::from(s.as_str()))
+            }
+        }
+    };
+}
+
+api_enum!(
+    /// Connection status of a probe
+    ///
+    ProbeStatus {
+        Connected => "Connected",
+        Disconnected => "Disconnected",
+        Abandoned => "Abandoned",
+        NeverConnected => "Never Connected",
+    }
+);
+
+api_enum!(
+    /// Type of a measurement or of its results
+    ///
+    MeasurementType {
+        Ping => "ping",
+        Traceroute => "traceroute",
+        Dns => "dns",
+        SslCert => "sslcert",
+        Http => "http",
+        Ntp => "ntp",
+    }
+);
+
+api_enum!(
+    /// Status of a measurement
+    ///
+    MeasurementState {
+        Specified => "Specified",
+        Scheduled => "Scheduled",
+        Ongoing => "Ongoing",
+        Stopped => "Stopped",
+        ForcedStop => "Forced to stop" | "Forced stop",
+        NoSuitableProbes => "No suitable probes",
+        Failed => "Failed",
+        Denied => "Denied",
+        Canceled => "Canceled" | "Cancelled",
+    }
+);
+
+impl MeasurementState {
+    /// States of a measurement which is running or about to
+    ///
+    pub const RUNNING: [MeasurementState; 3] = [
+        MeasurementState::Specified,
+        MeasurementState::Scheduled,
+        MeasurementState::Ongoing,
+    ];
+
+    /// Status ID used by the API for filtering (`status`, `status__in`)
+    ///
+    /// Example:
+    /// ```
+    /// # use atlas_rs::kinds::MeasurementState;
+    /// assert_eq!(Some(2), MeasurementState::Ongoing.id());
+    /// assert_eq!(None, MeasurementState::Unknown("Paused".to_string()).id());
+    /// ```
+    ///
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            MeasurementState::Specified => Some(0),
+            MeasurementState::Scheduled => Some(1),
+            MeasurementState::Ongoing => Some(2),
+            MeasurementState::Stopped => Some(4),
+            MeasurementState::ForcedStop => Some(5),
+            MeasurementState::NoSuitableProbes => Some(6),
+            MeasurementState::Failed => Some(7),
+            MeasurementState::Denied => Some(8),
+            MeasurementState::Canceled => Some(9),
+            MeasurementState::Unknown(_) => None,
+        }
+    }
+
+    /// Is the measurement running or about to?
+    ///
+    pub fn is_running(&self) -> bool {
+        Self::RUNNING.contains(self)
+    }
+
+    /// Is the measurement over, whatever the reason?  Unknown states are neither running
+    /// nor over.
+    ///
+    pub fn is_over(&self) -> bool {
+        matches!(
+            self,
+            MeasurementState::Stopped
+                | MeasurementState::ForcedStop
+                | MeasurementState::NoSuitableProbes
+                | MeasurementState::Failed
+                | MeasurementState::Denied
+                | MeasurementState::Canceled
+        )
+    }
+}
+
+api_enum!(
+    /// Type of an object as given in its `type` field (probes and anchors)
+    ///
+    ObjectType {
+        Probe => "Probe",
+        Anchor => "Anchor",
+    }
+);
+
+api_enum!(
+    /// Type of an API key
+    ///
+    KeyType {
+        User => "user",
+    }
+);
+
+api_enum!(
+    /// How probes are selected (measurement creation and participation requests)
+    ///
+    SelectionType {
+        Area => "area",
+        Country => "country",
+        Prefix => "prefix",
+        Asn => "asn",
+        Probes => "probes",
+        Msm => "msm",
+    }
+);
+
+api_enum!(
+    /// What a participation request does
+    ///
+    Action {
+        Add => "add",
+        Remove => "remove",
+    }
+);
+
+// ---------------------------------------------------------------------------
+
+/// Lowercase and drop everything but letters and digits
+///
+fn normalise(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("Connected", ProbeStatus::Connected)]
+    #[case("disconnected", ProbeStatus::Disconnected)]
+    #[case("Never Connected", ProbeStatus::NeverConnected)]
+    #[case("never-connected", ProbeStatus::NeverConnected)]
+    #[case("NeverConnected", ProbeStatus::NeverConnected)]
+    #[case("Hibernating", ProbeStatus::Unknown("Hibernating".to_string()))]
+    fn test_probe_status(#[case] s: &str, #[case] st: ProbeStatus) {
+        assert_eq!(st, ProbeStatus::from(s));
+    }
+
+    #[rstest]
+    #[case("Forced to stop", MeasurementState::ForcedStop)]
+    #[case("Forced-stop", MeasurementState::ForcedStop)]
+    #[case("No suitable probes", MeasurementState::NoSuitableProbes)]
+    #[case("No-suitable-probes", MeasurementState::NoSuitableProbes)]
+    #[case("Cancelled", MeasurementState::Canceled)]
+    #[case("ONGOING", MeasurementState::Ongoing)]
+    fn test_measurement_state(#[case] s: &str, #[case] st: MeasurementState) {
+        assert_eq!(st, MeasurementState::from(s));
+    }
+
+    #[test]
+    fn test_measurement_state_running() {
+        assert!(MeasurementState::Scheduled.is_running());
+        assert!(!MeasurementState::Scheduled.is_over());
+        assert!(MeasurementState::Denied.is_over());
+        assert!(!MeasurementState::Denied.is_running());
+
+        let s = MeasurementState::Unknown("Paused".to_string());
+        assert!(!s.is_running());
+        assert!(!s.is_over());
+        assert_eq!(None, s.id());
+    }
+
+    #[test]
+    fn test_compare_str() {
+        assert_eq!("ping", MeasurementType::Ping);
+        assert_eq!(MeasurementType::SslCert, "SSLCert");
+        assert_ne!(Action::Add, "remove");
+        assert_eq!("wifi", MeasurementType::Unknown("wifi".to_string()));
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!("ping      |", format!("{:<10}|", MeasurementType::Ping));
+        assert_eq!("Never Connected", ProbeStatus::NeverConnected.to_string());
+    }
+
+    #[test]
+    fn test_serde() {
+        #[derive(Deserialize, Serialize)]
+        struct T {
+            a: MeasurementType,
+            b: ObjectType,
+            c: Action,
+        }
+
+        let t: T = serde_json::from_str(r#"{"a":"Traceroute","b":"Satellite","c":"add"}"#).unwrap();
+        assert_eq!(MeasurementType::Traceroute, t.a);
+        assert_eq!(ObjectType::Unknown("Satellite".to_string()), t.b);
+        assert_eq!(Action::Add, t.c);
+
+        // Known values are normalised, unknown ones are kept as-is
+        assert_eq!(
+            r#"{"a":"traceroute","b":"Satellite","c":"add"}"#,
+            serde_json::to_string(&t).unwrap()
+        );
+
+        let t: Result<T, _> = serde_json::from_str(r#"{"a":1,"b":"Probe","c":"add"}"#);
+        assert!(t.is_err());
+    }
+}
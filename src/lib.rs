@@ -43,6 +43,7 @@
 //!
 //! - **flat-api**: Provides the flatter API calls (aka `c.get_probe(n)`, etc.)
 //! - **alt-api**: Provides an alternate set of API calls (`Probe::get(cl, n)`, etc.)
+//! - **mock**: Provides a local stand-in for the Atlas API to test against (`mock::MockServer`)
 //!
 //! [cargo-features]: https://doc.rust-lang.org/stable/cargo/reference/manifest.html#the-features-section
 //! [Go]: https://golang.org/
@@ -52,15 +53,24 @@
 //! [Rust]: https://rust-lang.org/
 //!
 
-use clap::{crate_name, crate_version};
-
+pub mod cache;
+pub mod cassette;
 pub mod client;
 pub mod common;
 pub mod core;
+pub mod entity;
 pub mod errors;
+pub mod ids;
+pub mod kinds;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod option;
 pub mod param;
+pub mod reader;
 pub mod request;
+pub mod stream;
+pub mod timestamp;
+pub mod transport;
 
 /// Basic version string for the API.
 ///
@@ -72,7 +82,7 @@ pub mod request;
 /// ```
 ///
 pub fn version() -> String {
-    format!("{}/{}", crate_name!(), crate_version!())
+    format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
 }
 
 #[cfg(test)]
@@ -83,6 +93,9 @@ mod tests {
     fn test_version() {
         let v = version();
 
-        assert_eq!(format!("{}/{}", crate_name!(), crate_version!()), v);
+        assert_eq!(
+            format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            v
+        );
     }
 }
@@ -0,0 +1,819 @@
+//! Module implementing a local, in-process, stand-in for the Atlas API.
+//!
+//! This is only available with the `mock` feature.  `MockServer::start()` listens on a random
+//! local port and answers the probes, anchors, keys, credits, measurements and results
+//! routes (see `APIDESIGN.md`) from a small set of seeded data:
+//!
+//! - 60 probes (`1..=60`) in FR, NL and DE, some of them anchors,
+//! - 3 anchors,
+//! - 2 keys,
+//! - credits,
+//! - measurement `1001`, an ongoing ping with results from probes `1..=5`.
+//!
+//! Lists are paginated (`page` & `page_size`, 50 by default), top-level fields can be used as
+//! filters (`?country_code=FR`), to sort (`sort=-id`) or to trim the answer (`fields=id,status`)
+//! and errors are sent in the `APIError` format.  Creating a
+//! measurement gives it fake results from the selected probes right away.
+//!
+//! Every request must carry the `MOCK_KEY` API key.
+//!
+//! Example:
+//! ```
+//! # #[cfg(feature = "mock")]
+//! # {
+//! use atlas_rs::core::probes::Probe;
+//! use atlas_rs::mock::MockServer;
+//!
+//! let srv = MockServer::start().unwrap();
+//! let c = srv.client();
+//!
+//! let p: Probe = c.probe().get(1).unwrap();
+//! assert_eq!(1, p.id);
+//! # }
+//! ```
+//!
+
+// Standard library
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// External crates
+use reqwest::Url;
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+// Our crates
+use crate::client::{Client, ClientBuilder};
+use crate::errors::APIError;
+
+// ---------------------------------------------------------------------------
+
+/// API key expected by the mock server
+pub const MOCK_KEY: &str = "mock-key";
+
+/// Base path of the API
+const API_PATH: &str = "/api/v2";
+
+/// Default page size
+const PAGE_SIZE: usize = 50;
+
+/// Number of seeded probes
+const NB_PROBES: u32 = 60;
+
+/// Seeded measurement
+const MSM_ID: u32 = 1001;
+
+/// Fixed "now" so answers are reproducible
+const NOW: i64 = 1652000000;
+
+/// Query parameters which are not filters
+const NOT_FILTERS: [&str; 11] = [
+    "key",
+    "page",
+    "page_size",
+    "format",
+    "start",
+    "stop",
+    "probe_ids",
+    "fields",
+    "optional_fields",
+    "include",
+    "sort",
+];
+
+// ---------------------------------------------------------------------------
+
+/// Local server, stopped when dropped
+///
+pub struct MockServer {
+    /// Where we listen
+    endpoint: String,
+    /// Server handle, to stop it
+    server: Arc<Server>,
+    /// Serving thread
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on a random local port with the seeded data
+    ///
+    pub fn start() -> Result<Self, APIError> {
+        let server = Server::http("127.0.0.1:0")
+            .map_err(|e| APIError::new(500, "Mock server", &e.to_string(), "MockServer"))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| APIError::new(500, "Mock server", "no address", "MockServer"))?;
+
+        let server = Arc::new(server);
+        let state = Arc::new(Mutex::new(State::seed()));
+
+        let srv = server.clone();
+        let handle = thread::spawn(move || {
+            for mut rq in srv.incoming_requests() {
+                let mut body = vec![];
+                let _ = rq.as_reader().read_to_end(&mut body);
+                let auth = rq
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .map(|h| h.value.to_string());
+
+                let (status, txt) = state.lock().unwrap().handle(
+                    rq.method().as_str(),
+                    rq.url(),
+                    auth.as_deref(),
+                    &body,
+                );
+
+                let ctype = Header::from_bytes("Content-Type", "application/json").unwrap();
+                let resp = Response::from_string(txt)
+                    .with_status_code(status)
+                    .with_header(ctype);
+                let _ = rq.respond(resp);
+            }
+        });
+
+        Ok(MockServer {
+            endpoint: format!("http://{}{}", addr, API_PATH),
+            server,
+            handle: Some(handle),
+        })
+    }
+
+    /// Endpoint to give to `ClientBuilder::endpoint()`
+    ///
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// A client already configured to talk to this server
+    ///
+    pub fn client(&self) -> Client {
+        ClientBuilder::new()
+            .api_key(MOCK_KEY)
+            .endpoint(&self.endpoint)
+            .build()
+            .unwrap()
+    }
+}
+
+impl Debug for MockServer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockServer")
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Everything the server knows about
+///
+#[derive(Debug)]
+struct State {
+    probes: Vec<Value>,
+    anchors: Vec<Value>,
+    keys: Vec<Value>,
+    credits: Value,
+    measurements: BTreeMap<u32, Value>,
+    results: BTreeMap<u32, Vec<Value>>,
+    next_msm: u32,
+}
+
+/// Answer of one route: status and JSON
+type Answer = (u16, Value);
+
+impl State {
+    /// Create all the seeded data
+    ///
+    fn seed() -> Self {
+        let countries = [("FR", 3215), ("NL", 3333), ("DE", 3320)];
+
+        let probes = (1..=NB_PROBES)
+            .map(|id| {
+                let (cc, asn) = countries[(id as usize - 1) % countries.len()];
+                json!({
+                    "address_v4": format!("192.0.2.{}", id),
+                    "address_v6": null,
+                    "asn_v4": asn,
+                    "asn_v6": null,
+                    "country_code": cc,
+                    "description": format!("Probe {}", id),
+                    "first_connected": NOW - 86400 * 365,
+                    "geometry": {"type": "Point", "coordinates": [2.0 + id as f64 / 10.0, 48.0]},
+                    "id": id,
+                    "is_anchor": id % 20 == 0,
+                    "is_public": true,
+                    "last_connected": NOW,
+                    "prefix_v4": "192.0.2.0/24",
+                    "prefix_v6": null,
+                    "status": {"since": "2022-05-01T00:00:00Z", "id": 1, "name": "Connected"},
+                    "status_since": NOW - 86400,
+                    "tags": [{"name": "system: IPv4 Works", "slug": "system-ipv4-works"}],
+                    "total_uptime": 86400 * 300,
+                    "type": "Probe",
+                })
+            })
+            .collect();
+
+        let anchors = (1..=3)
+            .map(|id| {
+                let (cc, asn) = countries[id as usize - 1];
+                json!({
+                    "id": id,
+                    "type": "Anchor",
+                    "fqdn": format!("{}-anchor.example.net", cc.to_lowercase()),
+                    "probe": id * 20,
+                    "is_ipv4_only": true,
+                    "ip_v4": format!("192.0.2.{}", id * 20),
+                    "as_v4": asn,
+                    "ip_v4_gateway": "192.0.2.1",
+                    "ip_v4_netmask": "255.255.255.0",
+                    "ip_v6": null,
+                    "as_v6": null,
+                    "ip_v6_gateway": null,
+                    "ip_v6_prefix": null,
+                    "city": "Somewhere",
+                    "country": cc,
+                    "geometry": {"type": "Point", "coordinates": [2.0, 48.0]},
+                    "tlsa_record": "",
+                    "is_disabled": false,
+                    "date_live": "2020-01-01",
+                    "hardware_version": 3,
+                })
+            })
+            .collect();
+
+        let keys = ["measurements", "probes"]
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                json!({
+                    "uuid": format!("00000000-0000-0000-0000-{:012}", i + 1),
+                    "valid_from": "2022-01-01T00:00:00Z",
+                    "valid_to": null,
+                    "enabled": true,
+                    "is_active": true,
+                    "created_at": "2022-01-01T00:00:00Z",
+                    "label": label,
+                    "grants": [{"permission": format!("people.{}", label), "target": null}],
+                    "type": "user",
+                })
+            })
+            .collect();
+
+        let credits = json!({
+            "current_balance": 1000000,
+            "credit_checked": true,
+            "max_daily_credits": 1000000,
+            "estimated_daily_income": 21600,
+            "estimated_daily_expenditure": 1440,
+            "estimated_daily_balance": 20160,
+            "calculation_time": "2022-05-08T12:00:00Z",
+            "estimated_runout_seconds": null,
+            "past_day_measurement_results": 120,
+            "past_day_credits_spent": 1440,
+            "last_date_debited": "2022-05-08",
+            "last_date_credited": "2022-05-08",
+            "income_items": "/api/v2/credits/incomes/",
+            "expense_items": "/api/v2/credits/expenses/",
+            "transactions": "/api/v2/credits/transactions/",
+        });
+
+        let mut st = State {
+            probes,
+            anchors,
+            keys,
+            credits,
+            measurements: BTreeMap::new(),
+            results: BTreeMap::new(),
+            next_msm: MSM_ID,
+        };
+        let def = json!({"type": "ping", "af": 4, "target": "k.root-servers.net",
+                         "description": "Ping to k.root-servers.net"});
+        st.create_measurement(&def, &[1, 2, 3, 4, 5], false);
+        st
+    }
+
+    /// Dispatch one request
+    ///
+    fn handle(
+        &mut self,
+        method: &str,
+        url: &str,
+        auth: Option<&str>,
+        body: &[u8],
+    ) -> (u16, String) {
+        let url = match Url::parse(&format!("http://localhost{}", url)) {
+            Ok(u) => u,
+            Err(e) => return answer((400, error(400, "Bad Request", &e.to_string()))),
+        };
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+
+        // Key either as header or as parameter
+        let key = auth
+            .and_then(|a| a.strip_prefix("Key "))
+            .map(|k| k.to_string())
+            .or_else(|| param(&query, "key"));
+        if key.as_deref() != Some(MOCK_KEY) {
+            return answer((403, error(403, "Forbidden", "Invalid API key.")));
+        }
+
+        let path = match url.path().strip_prefix(API_PATH) {
+            Some(p) => p.trim_matches('/').to_string(),
+            None => return answer(not_found()),
+        };
+        let body: Value = if body.is_empty() {
+            Value::Null
+        } else {
+            match serde_json::from_slice(body) {
+                Ok(v) => v,
+                Err(e) => return answer((400, error(400, "Bad Request", &e.to_string()))),
+            }
+        };
+
+        let seg: Vec<&str> = path.split('/').collect();
+        let res = match (method, seg.as_slice()) {
+            // Probes
+            ("GET", ["probes"]) => list(&self.probes, &url, &query),
+            ("GET", ["probes", id]) => get(&self.probes, "id", id),
+            ("PUT" | "PATCH", ["probes", id]) => update(&mut self.probes, "id", id, &body),
+            ("GET", ["probes", id, "measurements"]) => {
+                let id = id.parse::<u32>().unwrap_or(0);
+                let v: Vec<Value> = self
+                    .measurements
+                    .iter()
+                    .filter(|(m, _)| self.results[m].iter().any(|r| r["prb_id"] == json!(id)))
+                    .map(|(_, v)| v.clone())
+                    .collect();
+                list(&v, &url, &query)
+            }
+            // Anchors
+            ("GET", ["anchors"]) => list(&self.anchors, &url, &query),
+            ("GET", ["anchors", id]) => get(&self.anchors, "id", id),
+            // Keys
+            ("GET", ["keys"]) => list(&self.keys, &url, &query),
+            ("GET", ["keys", uuid]) => get(&self.keys, "uuid", uuid),
+            ("PUT" | "PATCH", ["keys", uuid]) => update(&mut self.keys, "uuid", uuid, &body),
+            ("DELETE", ["keys", uuid]) => {
+                let n = self.keys.len();
+                self.keys.retain(|k| k["uuid"] != json!(uuid));
+                if self.keys.len() < n {
+                    (204, Value::Null)
+                } else {
+                    not_found()
+                }
+            }
+            // Credits
+            ("GET", ["credits"]) => (200, self.credits.clone()),
+            // Measurements
+            ("GET", ["measurements"]) => {
+                let v: Vec<Value> = self.measurements.values().cloned().collect();
+                list(&v, &url, &query)
+            }
+            ("POST", ["measurements"]) => self.create(&body),
+            ("GET", ["measurements", id]) => self.measurement(id, |m, _| (200, m.clone())),
+            ("PATCH" | "PUT", ["measurements", id]) => {
+                let id = id.parse::<u32>().unwrap_or(0);
+                match self.measurements.get_mut(&id) {
+                    Some(m) => {
+                        merge(m, &body);
+                        (200, m.clone())
+                    }
+                    None => not_found(),
+                }
+            }
+            ("DELETE", ["measurements", id]) => {
+                let id = id.parse::<u32>().unwrap_or(0);
+                match self.measurements.get_mut(&id) {
+                    Some(m) => {
+                        m["status"] = json!({"id": 4, "name": "Stopped", "when": NOW});
+                        m["stop_time"] = json!(NOW);
+                        (204, Value::Null)
+                    }
+                    None => not_found(),
+                }
+            }
+            ("GET", ["measurements", id, "results"]) => {
+                return self.measurement_results(id, &query, false);
+            }
+            ("GET", ["measurements", id, "latest"]) => {
+                return self.measurement_results(id, &query, true);
+            }
+            (_, ["probes" | "anchors" | "keys" | "credits" | "measurements", ..]) => (
+                405,
+                error(
+                    405,
+                    "Method Not Allowed",
+                    &format!("Method \"{}\" not allowed.", method),
+                ),
+            ),
+            _ => not_found(),
+        };
+        answer(res)
+    }
+
+    /// Run `f` on an existing measurement
+    ///
+    fn measurement<F>(&self, id: &str, f: F) -> Answer
+    where
+        F: Fn(&Value, &[Value]) -> Answer,
+    {
+        let id = id.parse::<u32>().unwrap_or(0);
+        match self.measurements.get(&id) {
+            Some(m) => f(m, &self.results[&id]),
+            None => not_found(),
+        }
+    }
+
+    /// Results of a measurement, optionally only the latest per probe, as JSON or NDJSON
+    ///
+    fn measurement_results(
+        &self,
+        id: &str,
+        query: &[(String, String)],
+        latest: bool,
+    ) -> (u16, String) {
+        let (status, v) = self.measurement(id, |_, res| {
+            let start = param(query, "start").and_then(|s| s.parse::<i64>().ok());
+            let stop = param(query, "stop").and_then(|s| s.parse::<i64>().ok());
+            let probes: Vec<Value> = param(query, "probe_ids")
+                .map(|p| {
+                    p.split(',')
+                        .filter_map(|s| s.parse::<u32>().ok())
+                        .map(|p| json!(p))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut sel: Vec<Value> = res
+                .iter()
+                .filter(|r| start.is_none_or(|s| r["timestamp"].as_i64() >= Some(s)))
+                .filter(|r| stop.is_none_or(|s| r["timestamp"].as_i64() <= Some(s)))
+                .filter(|r| probes.is_empty() || probes.contains(&r["prb_id"]))
+                .cloned()
+                .collect();
+            if latest {
+                let mut last = BTreeMap::new();
+                for r in sel {
+                    last.insert(r["prb_id"].to_string(), r);
+                }
+                sel = last.into_values().collect();
+            }
+            (200, Value::Array(sel))
+        });
+
+        if status == 200 && param(query, "format").as_deref() == Some("txt") {
+            let lines: Vec<String> = v
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r.to_string())
+                .collect();
+            return (200, lines.join("\n") + "\n");
+        }
+        answer((status, v))
+    }
+
+    /// Create measurements, one per definition
+    ///
+    fn create(&mut self, body: &Value) -> Answer {
+        let defs = match body["definitions"].as_array() {
+            Some(d) if !d.is_empty() => d.clone(),
+            _ => {
+                return (
+                    400,
+                    error(400, "Bad Request", "definitions: This field is required."),
+                )
+            }
+        };
+        let oneoff = body["is_oneoff"].as_bool().unwrap_or(false);
+
+        // Select probes with the first matching ones
+        let mut probes = vec![];
+        for sel in body["probes"].as_array().cloned().unwrap_or_default() {
+            let n = sel["requested"].as_u64().unwrap_or(1) as usize;
+            let value = sel["value"].as_str().unwrap_or("WW").to_string();
+            let found: Vec<u32> = match sel["type"].as_str().unwrap_or("area") {
+                "probes" => value.split(',').filter_map(|s| s.parse().ok()).collect(),
+                "country" => self.select(|p| p["country_code"] == json!(value), n),
+                "asn" => self.select(|p| p["asn_v4"].as_u64() == value.parse().ok(), n),
+                _ => self.select(|_| true, n),
+            };
+            probes.extend(found);
+        }
+        if probes.is_empty() {
+            probes = self.select(|_| true, 1);
+        }
+
+        let ids: Vec<u32> = defs
+            .iter()
+            .map(|d| self.create_measurement(d, &probes, oneoff))
+            .collect();
+        (201, json!({ "measurements": ids }))
+    }
+
+    /// IDs of the first `n` probes matching
+    ///
+    fn select<F: Fn(&Value) -> bool>(&self, f: F, n: usize) -> Vec<u32> {
+        self.probes
+            .iter()
+            .filter(|p| f(p))
+            .take(n)
+            .filter_map(|p| p["id"].as_u64().map(|id| id as u32))
+            .collect()
+    }
+
+    /// Store one measurement and synthesise its results
+    ///
+    fn create_measurement(&mut self, def: &Value, probes: &[u32], oneoff: bool) -> u32 {
+        let id = self.next_msm;
+        self.next_msm += 1;
+
+        let mtype = def["type"].as_str().unwrap_or("ping").to_lowercase();
+        let target = def["target"].as_str().unwrap_or("example.net").to_string();
+        let status = if oneoff {
+            json!({"id": 4, "name": "Stopped", "when": NOW})
+        } else {
+            json!({"id": 2, "name": "Ongoing", "when": null})
+        };
+
+        let msm = json!({
+            "id": id,
+            "af": def["af"].as_u64().unwrap_or(4),
+            "creation_time": NOW,
+            "description": def["description"].as_str().unwrap_or(""),
+            "interval": if oneoff { Value::Null } else { json!(240) },
+            "is_oneoff": oneoff,
+            "is_public": def["is_public"].as_bool().unwrap_or(true),
+            "participant_count": probes.len(),
+            "probes_requested": probes.len(),
+            "probes_scheduled": probes.len(),
+            "probes": probes.iter().map(|p| json!({"id": p})).collect::<Vec<_>>(),
+            "resolve_on_probe": false,
+            "result": format!("{}/measurements/{}/results/", API_PATH, id),
+            "start_time": NOW,
+            "status": status,
+            "stop_time": if oneoff { json!(NOW) } else { Value::Null },
+            "target": target,
+            "target_asn": null,
+            "target_ip": "192.0.2.250",
+            "tags": def["tags"].as_array().cloned().unwrap_or_default(),
+            "type": mtype,
+        });
+
+        let results = probes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| fake_result(&mtype, id, *p, NOW + i as i64, &target))
+            .collect();
+
+        self.measurements.insert(id, msm);
+        self.results.insert(id, results);
+        id
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// One fake result of the given type, with numbers derived from the probe ID
+///
+fn fake_result(mtype: &str, msm: u32, prb: u32, ts: i64, target: &str) -> Value {
+    let rtt = 5.0 + (prb % 17) as f64;
+    let mut r = match mtype {
+        "traceroute" => json!({
+            "paris_id": 1, "proto": "UDP", "size": 48, "endtime": ts + 2,
+            "result": [
+                {"hop": 1, "result": [{"from": "192.0.2.1", "rtt": 1.0, "size": 28, "ttl": 255}]},
+                {"hop": 2, "result": [{"from": "192.0.2.250", "rtt": rtt, "size": 28, "ttl": 62}]},
+            ],
+        }),
+        "dns" => json!({
+            "proto": "UDP",
+            "result": {"ANCOUNT": 1, "ARCOUNT": 0, "ID": prb, "NSCOUNT": 0, "QDCOUNT": 1,
+                       "abuf": "", "rt": rtt, "size": 64},
+        }),
+        "sslcert" => json!({
+            "dst_port": "443", "method": "TLS", "ver": "1.3", "rt": rtt, "ttc": rtt / 2.0,
+            "cert": ["-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----"],
+        }),
+        "http" => json!({
+            "uri": format!("http://{}/", target),
+            "result": [{"af": 4, "bsize": 1024, "hsize": 256, "dst_addr": "192.0.2.250",
+                        "method": "GET", "res": 200, "rt": rtt, "ver": "1.1"}],
+        }),
+        "ntp" => json!({
+            "li": "no", "mode": "server", "poll": 8.0, "precision": 0.000001, "ref-id": "GPS",
+            "stratum": 1, "version": 4,
+            "result": [{"final-ts": 1.0, "offset": 0.001, "origin-ts": 1.0, "receive-ts": 1.0,
+                        "rtt": rtt / 1000.0, "transmit-ts": 1.0}],
+        }),
+        _ => json!({
+            "avg": rtt, "min": rtt - 0.5, "max": rtt + 0.5, "sent": 3, "rcvd": 3, "dup": 0, "size": 48,
+            "result": [{"rtt": rtt - 0.5}, {"rtt": rtt}, {"rtt": rtt + 0.5}],
+        }),
+    };
+
+    let obj = r.as_object_mut().unwrap();
+    obj.insert("fw".to_string(), json!(5020));
+    obj.insert("af".to_string(), json!(4));
+    obj.insert("dst_addr".to_string(), json!("192.0.2.250"));
+    obj.insert("dst_name".to_string(), json!(target));
+    obj.insert("from".to_string(), json!(format!("192.0.2.{}", prb)));
+    obj.insert("src_addr".to_string(), json!(format!("192.0.2.{}", prb)));
+    obj.insert("msm_id".to_string(), json!(msm));
+    obj.insert("prb_id".to_string(), json!(prb));
+    obj.insert("timestamp".to_string(), json!(ts));
+    obj.insert("type".to_string(), json!(mtype));
+    r
+}
+
+/// Paginate a list after applying the filters
+///
+fn list(all: &[Value], url: &Url, query: &[(String, String)]) -> Answer {
+    let mut filtered: Vec<&Value> = all
+        .iter()
+        .filter(|v| {
+            query
+                .iter()
+                .filter(|(k, _)| !NOT_FILTERS.contains(&k.as_str()))
+                .all(|(k, want)| match &v[k.as_str()] {
+                    Value::String(s) => s == want,
+                    Value::Null => false,
+                    other => &other.to_string() == want,
+                })
+        })
+        .collect();
+
+    if let Some(field) = param(query, "sort") {
+        let (field, desc) = match field.strip_prefix('-') {
+            Some(f) => (f.to_string(), true),
+            None => (field, false),
+        };
+        filtered.sort_by(|a, b| {
+            let o = compare(&a[field.as_str()], &b[field.as_str()]);
+            if desc {
+                o.reverse()
+            } else {
+                o
+            }
+        });
+    }
+
+    let page = param(query, "page")
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let size = param(query, "page_size")
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(PAGE_SIZE)
+        .max(1);
+
+    let fields = param(query, "fields");
+    let data: Vec<Value> = filtered
+        .iter()
+        .skip((page - 1) * size)
+        .take(size)
+        .map(|v| trim(v, fields.as_deref()))
+        .collect();
+    let link = |n: usize| {
+        let mut u = url.clone();
+        u.query_pairs_mut().clear();
+        for (k, v) in query.iter().filter(|(k, _)| k != "page" && k != "key") {
+            u.query_pairs_mut().append_pair(k, v);
+        }
+        u.query_pairs_mut().append_pair("page", &n.to_string());
+        Value::String(u.to_string())
+    };
+
+    let next = if page * size < filtered.len() {
+        link(page + 1)
+    } else {
+        Value::Null
+    };
+    let previous = if page > 1 {
+        link(page - 1)
+    } else {
+        Value::Null
+    };
+
+    (
+        200,
+        json!({
+            "count": filtered.len(),
+            "next": next,
+            "previous": previous,
+            "results": data,
+        }),
+    )
+}
+
+/// Order two values of the same field, numbers or strings
+///
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+/// Only keep the given comma-separated fields, `id` is always kept
+///
+fn trim(v: &Value, fields: Option<&str>) -> Value {
+    match (fields, v.as_object()) {
+        (Some(fields), Some(obj)) => {
+            let keep: Vec<&str> = fields.split(',').map(|f| f.trim()).collect();
+            Value::Object(
+                obj.iter()
+                    .filter(|(k, _)| k.as_str() == "id" || keep.contains(&k.as_str()))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            )
+        }
+        _ => v.clone(),
+    }
+}
+
+/// Find one element by its ID field
+///
+fn get(all: &[Value], field: &str, id: &str) -> Answer {
+    match all.iter().find(|v| same_id(&v[field], id)) {
+        Some(v) => (200, v.clone()),
+        None => not_found(),
+    }
+}
+
+/// Change some fields of one element
+///
+fn update(all: &mut [Value], field: &str, id: &str, body: &Value) -> Answer {
+    match all.iter_mut().find(|v| same_id(&v[field], id)) {
+        Some(v) => {
+            merge(v, body);
+            (200, v.clone())
+        }
+        None => not_found(),
+    }
+}
+
+/// Copy the fields of `from` into `to`
+///
+fn merge(to: &mut Value, from: &Value) {
+    if let (Some(to), Some(from)) = (to.as_object_mut(), from.as_object()) {
+        for (k, v) in from {
+            to.insert(k.clone(), v.clone());
+        }
+    }
+}
+
+/// IDs are numbers or strings
+///
+fn same_id(v: &Value, id: &str) -> bool {
+    match v {
+        Value::String(s) => s == id,
+        other => other.as_u64() == id.parse().ok(),
+    }
+}
+
+/// Value of a query parameter
+///
+fn param(query: &[(String, String)], name: &str) -> Option<String> {
+    query
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.clone())
+}
+
+/// Error body in the API format
+///
+fn error(status: u16, title: &str, detail: &str) -> Value {
+    json!({"error": {"status": status, "code": status, "detail": detail, "title": title}})
+}
+
+/// Standard 404
+///
+fn not_found() -> Answer {
+    (404, error(404, "Not Found", "Not found."))
+}
+
+/// Serialise the answer, no body for 204
+///
+fn answer((status, v): Answer) -> (u16, String) {
+    match v {
+        Value::Null => (status, String::new()),
+        v => (status, v.to_string()),
+    }
+}
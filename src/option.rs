@@ -1,69 +1,138 @@
 //! We define our own set of options to simplify the code dealing with them.
 //!
+//! Options are kept in the order they were added so the generated URLs are always the same
+//! (which matters for the cache and the cassettes).  A key can have more than one value,
+//! either as a comma-separated list (`id__in=1,2,3`, see `insert_list()`) or as a repeated
+//! parameter (`tags=a&tags=b`, see `append()`).  Values can be given as anything convertible
+//! into a `Param` (strings, numbers, booleans, IDs) and are percent-encoded when put into URLs.
+//!
+//! Example:
+//! ```
+//! use atlas_rs::option::Options;
+//!
+//! let mut o = Options::from([("country_code", "FR")]);
+//! o.insert_list("id__in", [1, 2, 3]);
+//! o.insert("is_public", true);
+//! o.append("tags", "home");
+//! o.append("tags", "ftth & co");
+//!
+//! assert_eq!(
+//!     "country_code=FR&id__in=1%2C2%2C3&is_public=true&tags=home&tags=ftth+%26+co",
+//!     o.to_query()
+//! );
+//! ```
+//!
 
 // Std library
-use std::collections::hash_map::{IntoValues, Iter, Keys, Values, ValuesMut};
-use std::collections::HashMap;
 use std::iter::{FromIterator, IntoIterator};
 use std::ops::{Index, IndexMut};
+use std::slice::Iter;
 
 // External crates
+use itertools::Itertools;
 
 // Our crates
-//
+use crate::param::Param;
 
-/// Our own option type
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Options(HashMap<String, String>);
+/// Our own option type, an ordered list of `(name, value)` pairs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options(Vec<(String, String)>);
 
 impl Options {
     #[inline]
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self(Vec::new())
     }
 
-    #[inline]
-    pub fn insert(&mut self, k: String, v: String) -> Option<String> {
-        self.0.insert(k, v)
+    /// Set the value of an option, replacing all the previous ones and keeping its position.
+    /// Returns the first previous value, if any.
+    ///
+    pub fn insert(&mut self, k: impl Into<String>, v: impl Into<Param>) -> Option<String> {
+        let k = k.into();
+        let old = self.get(&k).map(|s| s.to_string());
+        self.replace(&k, vec![String::from(v.into())]);
+        old
+    }
+
+    /// Set an option to a comma-separated list of values, like `id__in=1,2,3`
+    ///
+    pub fn insert_list<I>(&mut self, k: impl Into<String>, values: I) -> Option<String>
+    where
+        I: IntoIterator,
+        I::Item: Into<Param>,
+    {
+        let v = values.into_iter().map(|v| String::from(v.into())).join(",");
+        self.insert(k, v)
     }
 
-    /// Gets an iterator over the keys of the map.
+    /// Add one more value for an option, it will be repeated in the query
+    ///
+    pub fn append(&mut self, k: impl Into<String>, v: impl Into<Param>) -> &mut Self {
+        self.0.push((k.into(), String::from(v.into())));
+        self
+    }
+
+    /// First value of an option
+    ///
+    pub fn get(&self, k: &str) -> Option<&str> {
+        self.0.iter().find(|(n, _)| n == k).map(|(_, v)| v.as_str())
+    }
+
+    /// All the values of an option, in order
+    ///
+    pub fn get_all(&self, k: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(n, _)| n == k)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Remove an option and all its values, returning the first one
+    ///
+    pub fn remove(&mut self, k: &str) -> Option<String> {
+        let old = self.get(k).map(|s| s.to_string());
+        self.0.retain(|(n, _)| n != k);
+        old
+    }
+
+    /// Gets an iterator over the option names, each given once.
     #[inline]
-    pub fn keys(&self) -> Keys<'_, String, String> {
-        self.0.keys()
+    pub fn keys(&self) -> impl Iterator<Item = &String> + '_ {
+        self.0.iter().map(|(k, _)| k).unique()
     }
 
-    /// Gets an iterator over the values of the map.
+    /// Gets an iterator over the values.
     #[inline]
-    pub fn values(&self) -> Values<'_, String, String> {
-        self.0.values()
+    pub fn values(&self) -> impl Iterator<Item = &String> + '_ {
+        self.0.iter().map(|(_, v)| v)
     }
 
-    /// Gets an iterator over the values of the map.
+    /// Gets an iterator over the values as mutable.
     #[inline]
-    pub fn values_mut(&mut self) -> ValuesMut<'_, String, String> {
-        self.0.values_mut()
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut String> + '_ {
+        self.0.iter_mut().map(|(_, v)| v)
     }
 
-    /// Gets an iterator over the values of the map.
+    /// Consume the options and return the values.
     #[inline]
-    pub fn into_values(self) -> IntoValues<String, String> {
-        self.0.into_values()
+    pub fn into_values(self) -> impl Iterator<Item = String> {
+        self.0.into_iter().map(|(_, v)| v)
     }
 
-    /// Gets an iterator over the values of the map.
+    /// Gets an iterator over the `(name, value)` pairs, in order.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, String, String> {
+    pub fn iter(&self) -> Iter<'_, (String, String)> {
         self.0.iter()
     }
 
     /// Check is given option exist
     #[inline]
-    pub fn contains_key(self, s: &str) -> bool {
-        self.0.contains_key(s)
+    pub fn contains_key(&self, s: &str) -> bool {
+        self.0.iter().any(|(k, _)| k == s)
     }
 
-    /// Return the number of options
+    /// Return the number of `(name, value)` pairs
     #[inline]
     pub fn len(&self) -> usize {
         self.0.len()
@@ -75,24 +144,48 @@ impl Options {
         self.0.is_empty()
     }
 
-    /// Merge another set of option into our own
+    /// Merge another set of option into our own, their values replace ours
     ///
     pub fn merge(&mut self, o: &Options) -> &mut Self {
-        for (k, v) in o.iter() {
-            self.insert(k.clone(), v.clone());
+        for k in o.keys() {
+            let values = o.get_all(k).into_iter().map(|v| v.to_string()).collect();
+            self.replace(k, values);
         }
         self
     }
-}
 
-impl Default for Options {
-    fn default() -> Self {
-        Options::new()
+    /// Percent-encoded query string, without the leading `?`
+    ///
+    pub fn to_query(&self) -> String {
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.iter())
+            .finish()
+    }
+
+    /// Replace all the values of `k`, at the position of the first one if it exists
+    ///
+    fn replace(&mut self, k: &str, values: Vec<String>) {
+        let pos = self.0.iter().position(|(n, _)| n == k);
+        self.0.retain(|(n, _)| n != k);
+
+        let pairs = values.into_iter().map(|v| (k.to_string(), v));
+        match pos {
+            Some(i) => {
+                let tail = self.0.split_off(i);
+                self.0.extend(pairs);
+                self.0.extend(tail);
+            }
+            None => self.0.extend(pairs),
+        }
     }
 }
 
-impl<const N: usize> From<[(&str, &str); N]> for Options {
-    /// Used as a shortcut to `from_iter()`
+impl<K, V, const N: usize> From<[(K, V); N]> for Options
+where
+    K: Into<String>,
+    V: Into<Param>,
+{
+    /// Used as a shortcut to `from_iter()`, repeated names give repeated parameters
     ///
     /// Example:
     /// ```
@@ -100,33 +193,37 @@ impl<const N: usize> From<[(&str, &str); N]> for Options {
     /// let o = Options::from([("foo", "bar"), ("baz", "nope")]);
     ///
     /// assert_eq!(2, o.len());
+    ///
+    /// let o = Options::from([("page_size", 100)]);
+    ///
+    /// assert_eq!("100", o["page_size"]);
     /// ```
     ///
     #[inline]
-    fn from(arr: [(&str, &str); N]) -> Self {
-        let mut h = HashMap::new();
-        for (k, v) in arr.iter() {
-            h.insert(k.to_string(), v.to_string());
-        }
-        Options(h)
+    fn from(arr: [(K, V); N]) -> Self {
+        Options::from_iter(arr)
     }
 }
 
-impl<'a> FromIterator<(&'a str, &'a str)> for Options {
-    fn from_iter<T: IntoIterator<Item = (&'a str, &'a str)>>(iter: T) -> Self {
-        let mut h = HashMap::new();
+impl<K, V> FromIterator<(K, V)> for Options
+where
+    K: Into<String>,
+    V: Into<Param>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut o = Options::new();
         for (k, v) in iter {
-            h.insert(k.to_string(), v.to_string());
+            o.append(k, v);
         }
-        Options(h)
+        o
     }
 }
 
 impl<'a> IntoIterator for &'a Options {
-    type Item = (&'a String, &'a String);
-    type IntoIter = Iter<'a, String, String>;
+    type Item = &'a (String, String);
+    type IntoIter = Iter<'a, (String, String)>;
 
-    fn into_iter(self) -> Iter<'a, String, String> {
+    fn into_iter(self) -> Iter<'a, (String, String)> {
         self.0.iter()
     }
 }
@@ -136,6 +233,8 @@ impl<'a> IntoIterator for &'a Options {
 impl Index<&str> for Options {
     type Output = String;
 
+    /// Return the first value, panics if the option is not present.
+    ///
     /// Example:
     /// ```
     /// # use atlas_rs::option::Options;
@@ -146,14 +245,17 @@ impl Index<&str> for Options {
     ///
     #[inline]
     fn index(&self, index: &str) -> &Self::Output {
-        &self.0[&index.to_string()]
+        match self.0.iter().find(|(k, _)| k == index) {
+            Some((_, v)) => v,
+            None => panic!("no option named '{}'", index),
+        }
     }
 }
 
 /// Implement `IndexMut` on `Options` for accessing list elements as mutable objects.
 ///
 impl IndexMut<&str> for Options {
-    /// Access elements as mutable
+    /// Access the first value as mutable
     ///
     /// XXX If an element is not present, it will create it.
     ///
@@ -167,11 +269,14 @@ impl IndexMut<&str> for Options {
     ///
     #[inline]
     fn index_mut(&mut self, index: &str) -> &mut Self::Output {
-        let me = self.0.get_mut(index);
-        if me == None {
-            self.0.insert(index.to_string(), "".to_string());
-        }
-        self.0.get_mut(index).unwrap()
+        let i = match self.0.iter().position(|(k, _)| k == index) {
+            Some(i) => i,
+            None => {
+                self.0.push((index.to_string(), String::new()));
+                self.0.len() - 1
+            }
+        };
+        &mut self.0[i].1
     }
 }
 
@@ -189,6 +294,18 @@ mod tests {
         assert_eq!(s, *r);
     }
 
+    #[test]
+    fn test_options_merge_replace() {
+        let mut s1 = Options::from([("a", "1"), ("tags", "x"), ("tags", "y"), ("b", "2")]);
+        let s2 = Options::from([("tags", "z"), ("c", "3")]);
+
+        s1.merge(&s2);
+        assert_eq!(
+            Options::from([("a", "1"), ("tags", "z"), ("b", "2"), ("c", "3")]),
+            s1
+        );
+    }
+
     #[test]
     fn test_index() {
         let o = Options::from([("foo", "bar")]);
@@ -206,4 +323,39 @@ mod tests {
         o["baz"] = "hello".to_string();
         assert_eq!("hello", o["baz"]);
     }
+
+    #[test]
+    fn test_multi_values() {
+        let mut o = Options::new();
+        o.append("tags", "a").append("tags", "b");
+        o.insert("page", 2u32);
+
+        assert_eq!(vec!["a", "b"], o.get_all("tags"));
+        assert_eq!(Some("a"), o.get("tags"));
+        assert_eq!(vec!["tags", "page"], o.keys().collect::<Vec<_>>());
+        assert_eq!(3, o.len());
+
+        assert_eq!(Some("a".to_string()), o.insert("tags", "c"));
+        assert_eq!("tags=c&page=2", o.to_query());
+
+        assert_eq!(Some("c".to_string()), o.remove("tags"));
+        assert!(!o.contains_key("tags"));
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let mut o = Options::new();
+        o.insert("is_public", false);
+        o.insert("start", -1i64);
+        o.insert_list("id__in", [666u32, 1234]);
+
+        assert_eq!("is_public=false&start=-1&id__in=666%2C1234", o.to_query());
+    }
+
+    #[test]
+    fn test_encoding() {
+        let o = Options::from([("search", "a b&c=d/é")]);
+
+        assert_eq!("search=a+b%26c%3Dd%2F%C3%A9", o.to_query());
+    }
 }
@@ -1,10 +1,15 @@
 //! Module to manage API calls parameters and conversions.
 //!
+//! Conversions out of a `Param` are fallible: asking for a number from a string (or a negative
+//! value for an unsigned one) is an error, never a silent `0`.
+//!
 
 use std::fmt::{Display, Formatter};
 
 use serde::Serialize;
 
+use crate::errors::APIError;
+
 /// This enum is for passing the right kind of parameter to `get()`,
 /// there might be a better way for this.
 ///
@@ -20,12 +25,30 @@ pub enum Param {
     S(String),
 }
 
+/// Display the bare value, as it goes into URLs
+///
 impl Display for Param {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", serde_json::to_string(self).unwrap())
+        match self {
+            Param::I(v) => write!(f, "{}", v),
+            Param::U(v) => write!(f, "{}", v),
+            Param::L(v) => write!(f, "{}", v),
+            Param::S(s) => write!(f, "{}", s),
+        }
     }
 }
 
+/// Error for an impossible conversion
+///
+fn bad_param(p: &Param, what: &str) -> APIError {
+    APIError::new(
+        400,
+        "Bad parameter",
+        &format!("'{}' is not a valid {}", p, what),
+        "param",
+    )
+}
+
 // Implement From: for our enum to pass stuff around without explicitly converting before.
 
 /// From &str to Param
@@ -36,20 +59,36 @@ impl From<&str> for Param {
     }
 }
 
-/// From Param to String
+/// From String to Param
 ///
-impl<'a> From<Param> for String {
+impl From<String> for Param {
+    fn from(s: String) -> Self {
+        Param::S(s)
+    }
+}
+
+/// From Param to String, numbers are converted as well
+///
+impl From<Param> for String {
     fn from(p: Param) -> Self {
         match p {
             Param::S(s) => s,
-            _ => "".to_string(),
+            p => p.to_string(),
         }
     }
 }
 
+/// From bool to Param, as `true` or `false`
+///
+impl From<bool> for Param {
+    fn from(b: bool) -> Self {
+        Param::S(b.to_string())
+    }
+}
+
 /// From u32 to Param
 ///
-impl<'a> From<u32> for Param {
+impl From<u32> for Param {
     fn from(p: u32) -> Self {
         Param::U(p)
     }
@@ -57,18 +96,23 @@ impl<'a> From<u32> for Param {
 
 /// From Param to u32
 ///
-impl<'a> From<Param> for u32 {
-    fn from(p: Param) -> Self {
-        match p {
-            Param::U(v) => v,
-            _ => 0,
+impl TryFrom<Param> for u32 {
+    type Error = APIError;
+
+    fn try_from(p: Param) -> Result<Self, Self::Error> {
+        match &p {
+            Param::U(v) => Some(*v),
+            Param::I(v) => u32::try_from(*v).ok(),
+            Param::L(v) => u32::try_from(*v).ok(),
+            Param::S(s) => s.trim().parse().ok(),
         }
+        .ok_or_else(|| bad_param(&p, "u32"))
     }
 }
 
 /// From i64 to Param
 ///
-impl<'a> From<i64> for Param {
+impl From<i64> for Param {
     fn from(p: i64) -> Self {
         Param::L(p)
     }
@@ -76,7 +120,7 @@ impl<'a> From<i64> for Param {
 
 /// From i32 to Param
 ///
-impl<'a> From<i32> for Param {
+impl From<i32> for Param {
     fn from(p: i32) -> Self {
         Param::I(p)
     }
@@ -84,22 +128,62 @@ impl<'a> From<i32> for Param {
 
 /// From Param to i32
 ///
-impl<'a> From<Param> for i32 {
-    fn from(p: Param) -> Self {
-        match p {
-            Param::I(v) => v,
-            _ => 0,
+impl TryFrom<Param> for i32 {
+    type Error = APIError;
+
+    fn try_from(p: Param) -> Result<Self, Self::Error> {
+        match &p {
+            Param::I(v) => Some(*v),
+            Param::U(v) => i32::try_from(*v).ok(),
+            Param::L(v) => i32::try_from(*v).ok(),
+            Param::S(s) => s.trim().parse().ok(),
         }
+        .ok_or_else(|| bad_param(&p, "i32"))
     }
 }
 
 /// From Param to i64
 ///
-impl<'a> From<Param> for i64 {
-    fn from(p: Param) -> Self {
-        match p {
-            Param::L(v) => v,
-            _ => 0,
+impl TryFrom<Param> for i64 {
+    type Error = APIError;
+
+    fn try_from(p: Param) -> Result<Self, Self::Error> {
+        match &p {
+            Param::L(v) => Some(*v),
+            Param::I(v) => Some(i64::from(*v)),
+            Param::U(v) => Some(i64::from(*v)),
+            Param::S(s) => s.trim().parse().ok(),
         }
+        .ok_or_else(|| bad_param(&p, "i64"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!("666", Param::from(666u32).to_string());
+        assert_eq!("-1", Param::from(-1i32).to_string());
+        assert_eq!("foo", Param::from("foo").to_string());
+    }
+
+    #[test]
+    fn test_try_from_ok() {
+        assert_eq!(666u32, u32::try_from(Param::from("666")).unwrap());
+        assert_eq!(666u32, u32::try_from(Param::from(666i64)).unwrap());
+        assert_eq!(-1i32, i32::try_from(Param::from(-1i32)).unwrap());
+        assert_eq!(42i64, i64::try_from(Param::from(42u32)).unwrap());
+        assert_eq!("42", String::from(Param::from(42u32)));
+    }
+
+    #[test]
+    fn test_try_from_nok() {
+        let e = u32::try_from(Param::from("foo")).unwrap_err();
+        assert_eq!(400, e.error.status);
+
+        assert!(u32::try_from(Param::from(-1i32)).is_err());
+        assert!(i32::try_from(Param::from(1i64 << 40)).is_err());
     }
 }
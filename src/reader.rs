@@ -0,0 +1,326 @@
+//! Module to read measurement results in the NDJSON format (one JSON result per line).
+//!
+//! This is what the API sends back for `format=txt` and what the daily dumps contain.  As
+//! some measurements have millions of results, lines are decoded one at a time from any
+//! `BufRead` so memory usage does not depend on the number of results.
+//!
+//! Files can be compressed with gzip or bzip2, this is detected from the first bytes and
+//! not from the file name, so the daily dumps published by RIPE can be read as-is.  A
+//! `ResultFilter` keeps only the results we are interested in.
+//!
+//! Example:
+//! ```no_run
+//! use atlas_rs::reader::ResultReader;
+//!
+//! let rd = ResultReader::from_file("ping-2022-05-08.bz2").unwrap();
+//!
+//! for r in rd {
+//!     println!("{}", r.unwrap());
+//! }
+//! ```
+//!
+//! Filtering a daily dump:
+//! ```no_run
+//! use atlas_rs::reader::{ResultFilter, ResultReader};
+//!
+//! let f = ResultFilter::new().msm(1001).probe(666).since(1652000000);
+//! let rd = ResultReader::from_file("ping-2022-05-08.bz2").unwrap().filter_by(f);
+//!
+//! println!("{} results", rd.filter(|r| r.is_ok()).count());
+//! ```
+//!
+
+// Standard library
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+// External crates
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+// Our crates
+use crate::core::results::MeasurementResult;
+use crate::errors::APIError;
+use crate::ids::{MeasurementId, ProbeId};
+use crate::kinds::MeasurementType;
+use crate::timestamp::Timestamp;
+
+// ---------------------------------------------------------------------------
+
+/// Magic bytes at the beginning of a gzip stream
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Magic bytes at the beginning of a bzip2 stream
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// Any source of results we can read from
+pub type Source = Box<dyn BufRead + Send>;
+
+// ---------------------------------------------------------------------------
+
+/// Criteria to select results, all of the given ones must match.
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResultFilter {
+    /// Measurement IDs
+    msm: Vec<MeasurementId>,
+    /// Probe IDs
+    probes: Vec<ProbeId>,
+    /// Result type (ping, dns, etc.)
+    mtype: Option<MeasurementType>,
+    /// Earliest timestamp, included
+    since: Option<Timestamp>,
+    /// Latest timestamp, excluded
+    until: Option<Timestamp>,
+}
+
+impl ResultFilter {
+    /// Empty filter, everything matches
+    ///
+    pub fn new() -> Self {
+        ResultFilter::default()
+    }
+
+    /// Only results of this measurement, can be given more than once
+    ///
+    pub fn msm(mut self, id: impl Into<MeasurementId>) -> Self {
+        self.msm.push(id.into());
+        self
+    }
+
+    /// Only results from this probe, can be given more than once
+    ///
+    pub fn probe(mut self, id: impl Into<ProbeId>) -> Self {
+        self.probes.push(id.into());
+        self
+    }
+
+    /// Only results of this type
+    ///
+    pub fn mtype(mut self, mtype: impl Into<MeasurementType>) -> Self {
+        self.mtype = Some(mtype.into());
+        self
+    }
+
+    /// Only results at or after this time
+    ///
+    pub fn since(mut self, ts: impl Into<Timestamp>) -> Self {
+        self.since = Some(ts.into());
+        self
+    }
+
+    /// Only results before this time
+    ///
+    pub fn until(mut self, ts: impl Into<Timestamp>) -> Self {
+        self.until = Some(ts.into());
+        self
+    }
+
+    /// Check one result
+    ///
+    pub fn matches(&self, r: &MeasurementResult) -> bool {
+        (self.msm.is_empty() || self.msm.contains(&r.msm_id()))
+            && (self.probes.is_empty() || self.probes.contains(&r.prb_id()))
+            && self.mtype.as_ref().is_none_or(|t| *t == r.mtype())
+            && self.since.is_none_or(|ts| r.timestamp() >= ts)
+            && self.until.is_none_or(|ts| r.timestamp() < ts)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Iterator over the results found in a NDJSON source, empty lines are skipped and a line
+/// that can not be decoded gives an error without stopping the iteration.
+///
+#[derive(Debug)]
+pub struct ResultReader<R: BufRead> {
+    /// Where we read from
+    rd: R,
+    /// What we keep
+    filter: ResultFilter,
+    /// Current line, reused for every line
+    line: String,
+    /// Current line number, for errors
+    lineno: usize,
+}
+
+impl<R: BufRead> ResultReader<R> {
+    /// Read from an already uncompressed source
+    ///
+    /// Example:
+    /// ```
+    /// use atlas_rs::reader::ResultReader;
+    ///
+    /// let txt = r#"{"fw":5020,"prb_id":1,"msm_id":1001,"timestamp":0,"type":"ping","result":[]}"#;
+    /// let res: Vec<_> = ResultReader::new(txt.as_bytes()).collect();
+    ///
+    /// assert_eq!(1, res.len());
+    /// ```
+    ///
+    pub fn new(rd: R) -> Self {
+        ResultReader {
+            rd,
+            filter: ResultFilter::new(),
+            line: String::new(),
+            lineno: 0,
+        }
+    }
+
+    /// Only return the results matching the filter, errors are always returned
+    ///
+    pub fn filter_by(mut self, f: ResultFilter) -> Self {
+        self.filter = f;
+        self
+    }
+}
+
+impl ResultReader<Source> {
+    /// Read from any source, uncompressing it if needed
+    ///
+    pub fn from_reader<T>(rd: T) -> Result<Self, APIError>
+    where
+        T: Read + Send + 'static,
+    {
+        Ok(ResultReader::new(decompress(rd)?))
+    }
+
+    /// Open a file, possibly compressed
+    ///
+    pub fn from_file<P: AsRef<Path>>(fname: P) -> Result<Self, APIError> {
+        let fh = File::open(fname.as_ref())?;
+        Self::from_reader(fh)
+    }
+}
+
+impl<R: BufRead> Iterator for ResultReader<R> {
+    type Item = Result<MeasurementResult, APIError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            self.lineno += 1;
+            match self.rd.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<MeasurementResult>(line) {
+                Ok(r) if self.filter.matches(&r) => return Some(Ok(r)),
+                Ok(_) => continue,
+                Err(e) => {
+                    return Some(Err(APIError::new(
+                        500,
+                        "json/decode",
+                        &format!("line {}: {}", self.lineno, e),
+                        "ResultReader",
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Look at the first bytes to find out whether the source is compressed and wrap it with
+/// the right decoder.
+///
+fn decompress<T>(rd: T) -> Result<Source, APIError>
+where
+    T: Read + Send + 'static,
+{
+    let mut rd = BufReader::new(rd);
+    let head = rd.fill_buf()?;
+
+    let src: Source = if head.starts_with(GZIP_MAGIC) {
+        Box::new(BufReader::new(MultiGzDecoder::new(rd)))
+    } else if head.starts_with(BZIP2_MAGIC) {
+        Box::new(BufReader::new(MultiBzDecoder::new(rd)))
+    } else {
+        Box::new(rd)
+    };
+    Ok(src)
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+
+    use super::*;
+
+    const NDJSON: &str = r#"{"fw":5020,"msm_id":1001,"prb_id":1,"timestamp":1652000000,"type":"ping","result":[{"rtt":9.3}]}
+
+{"fw":5020,"msm_id":1001,"prb_id":2,"timestamp":1652000010,"type":"ping","result":[{"x":"*"}]}
+not json
+{"fw":5020,"msm_id":1001,"prb_id":3,"timestamp":1652000020,"type":"ping","result":[]}
+"#;
+
+    fn check(rd: ResultReader<Source>) {
+        let res: Vec<_> = rd.collect();
+
+        assert_eq!(4, res.len());
+        assert_eq!(2, res[1].as_ref().unwrap().prb_id());
+        let err = res[2].as_ref().unwrap_err();
+        assert!(err.error.detail.starts_with("line 4:"));
+        assert_eq!(3, res[3].as_ref().unwrap().prb_id());
+    }
+
+    #[test]
+    fn test_plain() {
+        check(ResultReader::from_reader(NDJSON.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_gzip() {
+        let mut enc = GzEncoder::new(vec![], flate2::Compression::default());
+        enc.write_all(NDJSON.as_bytes()).unwrap();
+        let data = enc.finish().unwrap();
+
+        check(ResultReader::from_reader(std::io::Cursor::new(data)).unwrap());
+    }
+
+    #[test]
+    fn test_bzip2() {
+        let mut enc = BzEncoder::new(vec![], bzip2::Compression::default());
+        enc.write_all(NDJSON.as_bytes()).unwrap();
+        let data = enc.finish().unwrap();
+
+        check(ResultReader::from_reader(std::io::Cursor::new(data)).unwrap());
+    }
+
+    #[test]
+    fn test_filter() {
+        let f = ResultFilter::new()
+            .mtype("PING")
+            .probe(2)
+            .probe(3)
+            .since(1652000010)
+            .until(1652000020);
+        let res: Vec<_> = ResultReader::new(NDJSON.as_bytes()).filter_by(f).collect();
+
+        // The bad line is still reported
+        assert_eq!(2, res.len());
+        assert_eq!(2, res[0].as_ref().unwrap().prb_id());
+        assert!(res[1].is_err());
+    }
+
+    #[test]
+    fn test_filter_msm() {
+        let f = ResultFilter::new().msm(1002);
+        let res = ResultReader::new(NDJSON.as_bytes())
+            .filter_by(f)
+            .filter(|r| r.is_ok())
+            .count();
+
+        assert_eq!(0, res);
+    }
+}
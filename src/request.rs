@@ -14,31 +14,36 @@
 
 // Std library
 //
-use std::fmt::Display;
+use std::marker::PhantomData;
 
 // External crates
 //
 use anyhow::Result;
-use itertools::Itertools;
-use serde::de;
+use serde::{de, Serialize};
 
 // Our internal crates.
 //
-use crate::client::{Client, Ctx};
-use crate::common::Routing;
+use crate::client::Client;
+use crate::common::{decode_response, Pages};
 use crate::core::{
-    anchor_measurements::AnchorMeasurement, anchors::Anchor, credits::Credits, keys::Key,
-    measurements::Measurement, participation_requests::ParticipationRequests, probes::Probe,
+    credits::{Credits, ExpenseItems},
+    measurements::Measurement,
+    participation_requests::{ParticipationChange, ParticipationRequests, RequestIds},
+    probes::{ArchivedProbe, Probe, Ranking, Tag, TagInfo},
+    results::{MeasurementResult, StatusCheck},
 };
+use crate::entity::Entity;
 use crate::errors::APIError;
+use crate::ids::{MeasurementId, ProbeId};
 use crate::option::Options;
-use crate::param::Param;
+use crate::reader::{ResultReader, Source};
+use crate::transport::{HttpRequest, HttpResponse, Method};
 
 // ------------------------------------------------------------
 
 /// All operations available
 ///
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Op {
     Archive,
     Claim,
@@ -48,13 +53,16 @@ pub enum Op {
     Get,
     Incomes,
     Info,
+    Latest,
     List,
     Measurement,
     Members,
     Permissions,
     Rankings,
+    Results,
     Set,
     Slugs,
+    StatusCheck,
     Tags,
     Targets,
     Transactions,
@@ -62,18 +70,28 @@ pub enum Op {
     Update,
 }
 
-// Dispatch table for the various operations in the different contexts.
-//
-fn get_ops_url<T: Display>(ctx: &Ctx, op: Op, p: T) -> String {
-    match ctx {
-        Ctx::AnchorMeasurements => AnchorMeasurement::set_url(op, p),
-        Ctx::Anchors => Anchor::set_url(op, p),
-        Ctx::Credits => Credits::set_url(op, p),
-        Ctx::Keys => Key::set_url(op, p),
-        Ctx::Measurements => Measurement::set_url(op, p),
-        Ctx::ParticipationRequests => ParticipationRequests::set_url(op, p),
-        Ctx::Probes => Probe::set_url(op, p),
-        Ctx::None => panic!("should not happen"),
+impl Op {
+    /// Path segment for the calls not on the object itself (`/probes/rankings/`,
+    /// `/measurements/1001/results/`, etc.)
+    ///
+    pub(crate) fn segment(&self) -> Option<&'static str> {
+        match self {
+            Op::Archive => Some("archive"),
+            Op::Claim => Some("members/claim"),
+            Op::Expenses => Some("expenses"),
+            Op::Incomes => Some("incomes"),
+            Op::Latest => Some("latest"),
+            Op::Measurement => Some("measurements"),
+            Op::Members => Some("members"),
+            Op::Permissions => Some("permissions"),
+            Op::Rankings => Some("rankings"),
+            Op::Results => Some("results"),
+            Op::StatusCheck => Some("status-check"),
+            Op::Tags => Some("tags"),
+            Op::Transactions => Some("transactions"),
+            Op::Transfers => Some("transfers"),
+            _ => None,
+        }
     }
 }
 
@@ -83,31 +101,34 @@ fn get_ops_url<T: Display>(ctx: &Ctx, op: Op, p: T) -> String {
 
 /// This is the chaining struct, containing all the state we are interesting in passing around.
 /// We do not need a special `Request` singleton (like for `Client` as most of what we need to
-/// pass around will be stored in either `cl` (the `Client`) or `r` (the `reqwest::Request` struct).
+/// pass around will be stored in either `cl` (the `Client`) or `r` (the `HttpRequest` struct).
+///
+/// The type parameter is the entity we are targetting (`Probe` for `/probes/`, etc.) and
+/// gives both the paths and the calls available.
 ///
 #[derive(Debug)]
-pub struct RequestBuilder {
-    /// Context is which part of the API we are targetting (`/probe/`, etc.)
-    pub ctx: Ctx,
+pub struct RequestBuilder<E: Entity> {
     /// Do we return paginated results?
     pub paged: bool,
     /// Client for API calls
     pub c: Client,
     /// Build our request here
-    pub r: reqwest::blocking::Request,
+    pub r: HttpRequest,
+    /// Entity we are targetting
+    entity: PhantomData<E>,
 }
 
 /// Add methods for chaining and keeping state.
 ///
-impl RequestBuilder {
+impl<E: Entity> RequestBuilder<E> {
     /// Create an empty struct RequestBuilder
     ///
-    pub fn new(ctx: Ctx, c: Client, r: reqwest::blocking::Request) -> Self {
+    pub fn new(c: Client, r: HttpRequest) -> Self {
         RequestBuilder {
-            ctx,
             paged: false,
             c,
             r,
+            entity: PhantomData,
         }
     }
 
@@ -117,11 +138,11 @@ impl RequestBuilder {
     /// These methods expect to be called by one of the main "categories" methods like
     /// `probes()` or `keys()`.  That way, context is established znd propagated.
     ///
-    /// In essence, these is the main router.  See [./APIDESIGN.md] for the list of methods
-    /// and which is called in which context.
+    /// The paths all come from the `Entity` implementation of the object, see
+    /// [./APIDESIGN.md] for the list of methods and which is called in which context.
     ///
-    /// Some calls have a parameter (type is `Param`) and it gets converted into the proper
-    /// type automatically depending on the `dispatch` function wants to get.
+    /// Calls on a single object take its ID type (`ProbeId` for probes, etc.) or anything
+    /// converting into it like a plain number.
     ///
     /// This is the `get` method for single results and a parameter.
     ///
@@ -137,39 +158,8 @@ impl RequestBuilder {
     /// # ;
     /// ```
     ///
-    pub fn get<T>(&mut self, data: impl Into<Param> + Display) -> Result<T, APIError>
-    where
-        T: de::DeserializeOwned + Display,
-    {
-        // Get the parameter
-        let add = get_ops_url(&self.ctx, Op::Get, data);
-
-        let opts = self.c.opts.iter();
-
-        // Setup URL with potential parameters like `key`.
-        let url = reqwest::Url::parse_with_params(
-            format!("{}{}", self.r.url().as_str(), add).as_str(),
-            opts,
-        )
-        .unwrap();
-
-        self.r = reqwest::blocking::Request::new(self.r.method().clone(), url);
-        let resp = self
-            .c
-            .agent
-            .as_ref()
-            .unwrap()
-            .get(self.r.url().as_str())
-            .send()?;
-
-        println!("{:?} - {:?}", self.c.opts, self.r.url().as_str());
-
-        let txt = resp.text()?;
-        println!("after text={}", txt);
-
-        let r: T = serde_json::from_str(&txt)?;
-        println!("after r={}", r);
-        Ok(r)
+    pub fn get(&mut self, id: impl Into<E::Id>) -> Result<E, APIError> {
+        self.c.get::<E>(id)
     }
 
     /// This is the `list` method which return a set of results.
@@ -183,95 +173,179 @@ impl RequestBuilder {
     /// let mut c = ClientBuilder::new().api_key("FOO").build().unwrap();
     ///
     ///
-    /// let res: Vec<Probe> = c.probe().list(0).unwrap()
+    /// let res: Vec<Probe> = c.probe().list().unwrap()
     /// # ;
     /// ```
     ///
-    pub fn list<T>(&mut self, data: impl Into<Param> + Display) -> Result<Vec<T>, APIError>
-    where
-        T: de::DeserializeOwned + std::fmt::Display + std::fmt::Debug,
-    {
+    pub fn list(&mut self) -> Result<Vec<E>, APIError> {
         self.paged = true;
-        // Main routing
-
-        // Get the parameter
-        let add = get_ops_url(&self.ctx, Op::List, data.into());
 
-        let opts = self.c.opts.iter();
-
-        // Setup URL with potential parameters like `key`.
-        let url = reqwest::Url::parse_with_params(
-            format!("{}{}", self.r.url().as_str(), add).as_str(),
-            opts,
-        )
-        .unwrap();
-
-        self.r = reqwest::blocking::Request::new(self.r.method().clone(), url);
-        let resp = self
-            .c
-            .agent
-            .as_ref()
-            .unwrap()
-            .get(self.r.url().as_str())
-            .send()?;
-
-        println!("{:?} - {:?}", self.c.opts, self.r.url().as_str());
+        // Options from `with()` and friends are already in our copy of the client
+        self.c.list::<E>(Options::new())
+    }
 
-        let txt = resp.text()?;
-        println!("after text={}", txt);
+    /// This is the `create` method, `POST`-ing the given body as JSON.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use serde_json::{json, Value};
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let body = json!({"definitions": [], "probes": [], "is_oneoff": true});
+    /// let res: Value = c.measurement().create(&body).unwrap()
+    /// # ;
+    /// ```
+    ///
+    pub fn create<B, T>(self, body: &B) -> Result<T, APIError>
+    where
+        B: Serialize,
+        T: de::DeserializeOwned,
+    {
+        let path = E::url(Op::Create, None)?;
+        self.send_json(Method::Post, &path, body)
+    }
 
-        let r: Vec<T> = serde_json::from_str(&txt)?;
-        println!("after r={:?}", r);
-        Ok(r)
+    /// This is the `set` method, replacing an object with a `PUT` of the given body.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::{Probe, ProbeUpdate};
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let u = ProbeUpdate::new().description("My probe").is_public(true);
+    /// let p: Probe = c.probe().set(666, &u).unwrap()
+    /// # ;
+    /// ```
+    ///
+    pub fn set<B, T>(self, id: impl Into<E::Id>, body: &B) -> Result<T, APIError>
+    where
+        B: Serialize,
+        T: de::DeserializeOwned,
+    {
+        let path = E::url(Op::Set, Some(&id.into()))?;
+        self.send_json(Method::Put, &path, body)
     }
 
-    /// This is the `info` method close to `get` but without a parameter.
+    /// This is the `update` method, modifying only the fields present in the body
+    /// with a `PATCH`.
     ///
     /// Example:
     ///
     /// ```no_run
     /// # use atlas_rs::client::ClientBuilder;
-    /// # use atlas_rs::core::keys::Key;
+    /// # use atlas_rs::core::probes::{Probe, ProbeUpdate};
     ///
-    /// let mut c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
     ///
-    /// let res: Key = c.keys().info().unwrap()
+    /// let u = ProbeUpdate::new().tags(&["home", "ftth"]);
+    /// let p: Probe = c.probe().update(666, &u).unwrap()
     /// # ;
     /// ```
     ///
-    pub fn info<T>(mut self) -> Result<T, APIError>
+    pub fn update<B, T>(self, id: impl Into<E::Id>, body: &B) -> Result<T, APIError>
+    where
+        B: Serialize,
+        T: de::DeserializeOwned,
+    {
+        let path = E::url(Op::Update, Some(&id.into()))?;
+        self.send_json(Method::Patch, &path, body)
+    }
+
+    /// This is the `delete` method, removing an object.  For measurements, this is how
+    /// they are stopped.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// c.measurement().delete(1001).unwrap();
+    /// ```
+    ///
+    pub fn delete(mut self, id: impl Into<E::Id>) -> Result<(), APIError> {
+        let url = self.url_for(&E::url(Op::Delete, Some(&id.into()))?)?;
+
+        let resp = self.call(Method::Delete, url, None)?;
+
+        if resp.is_success() {
+            Ok(())
+        } else {
+            Err(resp.json::<APIError>()?)
+        }
+    }
+
+    /// Fetch a single, non-paginated, answer for the given path.
+    ///
+    fn fetch<T>(mut self, path: &str) -> Result<T, APIError>
     where
-        T: de::DeserializeOwned + std::fmt::Display,
+        T: de::DeserializeOwned,
     {
-        // Get the parameter
-        let add = get_ops_url(&self.ctx, Op::Info, 0u32);
+        let url = self.url_for(path)?;
 
-        let opts = self.c.opts.iter();
+        let resp = self.call(Method::Get, url, None)?;
 
-        // Setup URL with potential parameters like `key`.
-        let url = reqwest::Url::parse_with_params(
-            format!("{}{}", self.r.url().as_str(), add).as_str(),
-            opts,
-        )
-        .unwrap();
+        decode_response(resp)
+    }
+
+    /// Walk through all the pages of the list at the given path, fetching them as needed.
+    ///
+    fn pages<T>(mut self, path: &str) -> Result<Pages<T>, APIError>
+    where
+        T: de::DeserializeOwned,
+    {
+        self.paged = true;
 
-        self.r = reqwest::blocking::Request::new(self.r.method().clone(), url);
-        let resp = self
-            .c
-            .agent
-            .as_ref()
-            .unwrap()
-            .get(self.r.url().as_str())
-            .send()?;
+        let url = self.url_for(path)?;
+        Ok(Pages::new(&self.c, url.to_string()))
+    }
+
+    /// Send the body as JSON with the given method and decode the answer.
+    ///
+    fn send_json<B, T>(mut self, method: Method, path: &str, body: &B) -> Result<T, APIError>
+    where
+        B: Serialize,
+        T: de::DeserializeOwned,
+    {
+        let url = self.url_for(path)?;
 
-        println!("{:?} - {:?}", self.c.opts, self.r.url().as_str());
+        let body = serde_json::to_vec(body)?;
+        let resp = self.call(method, url, Some(body))?;
 
-        let txt = resp.text()?;
-        println!("after text={}", txt);
+        decode_response(resp)
+    }
 
-        let r: T = serde_json::from_str(&txt)?;
-        println!("after r={}", r);
-        Ok(r)
+    /// Send the request through the client transport, keeping it in `r`.
+    ///
+    fn call(
+        &mut self,
+        method: Method,
+        url: reqwest::Url,
+        body: Option<Vec<u8>>,
+    ) -> Result<HttpResponse, APIError> {
+        let mut r = self.c.http_request(method, url);
+        if let Some(body) = body {
+            r = r.json(body);
+        }
+        self.r = r.clone();
+        self.c.send(r)
+    }
+
+    /// Generate the full URL for the given path with all the options.
+    ///
+    fn url_for(&self, add: &str) -> Result<reqwest::Url, APIError> {
+        reqwest::Url::parse_with_params(
+            format!("{}{}", self.r.url.as_str(), add).as_str(),
+            self.c.opts.iter(),
+        )
+        .map_err(|e| APIError::new(400, "Bad URL", &e.to_string(), "url_for"))
     }
 
     /// Makes it easy to specify options
@@ -286,7 +360,7 @@ impl RequestBuilder {
     ///
     /// let res: Vec<Probe> = c.probe()
     ///                        .with([("opt1", "foo"), ("opt2", "bar")])
-    ///                        .list(0u32)
+    ///                        .list()
     ///                        .unwrap()
     /// # ;
     /// ```
@@ -295,9 +369,373 @@ impl RequestBuilder {
         self.c.opts.merge(&opts.into());
         self
     }
+
+    /// Only ask for these fields (`fields=`), the others are `None` in the returned objects.
+    /// This makes listing all probes much faster.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::Probe;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let res: Vec<Probe> = c.probe()
+    ///     .fields(&["id", "status", "country_code"])
+    ///     .sort("-id")
+    ///     .list()
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn fields(self, fields: &[&str]) -> Self {
+        self.list_opt("fields", fields)
+    }
+
+    /// Also ask for fields not sent by default (`optional_fields=`) like the participating
+    /// probes of a measurement
+    ///
+    pub fn optional_fields(self, fields: &[&str]) -> Self {
+        self.list_opt("optional_fields", fields)
+    }
+
+    /// Expand the related objects (`include=`) instead of getting their URL, see `Link`
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::anchor_measurements::AnchorMeasurement;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let res: Vec<AnchorMeasurement> = c.anchor_measurement()
+    ///     .include(&["target", "measurement"])
+    ///     .list()
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn include(self, objs: &[&str]) -> Self {
+        self.list_opt("include", objs)
+    }
+
+    /// Sort the results on this field (`sort=`), descending if prefixed with `-`
+    ///
+    pub fn sort(mut self, field: &str) -> Self {
+        self.c.opts["sort"] = field.to_string();
+        self
+    }
+
+    /// Set a comma-separated option, nothing is set for an empty list
+    ///
+    fn list_opt(mut self, name: &str, values: &[&str]) -> Self {
+        if !values.is_empty() {
+            self.c.opts.insert_list(name, values.iter().copied());
+        }
+        self
+    }
+}
+
+// ------------------------------------------------------------
+
+/// Calls specific to probes
+///
+impl RequestBuilder<Probe> {
+    /// This is the `archive` method, returning a snapshot of all probes for the given day
+    /// (`YYYY-MM-DD`) as an iterator.  Pages are fetched as needed.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::ArchivedProbe;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let old: Vec<ArchivedProbe> = c.probe()
+    ///     .archive("2022-05-01")
+    ///     .unwrap()
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn archive(mut self, date: &str) -> Result<Pages<ArchivedProbe>, APIError> {
+        self.c.opts["date"] = date.to_string();
+
+        let path = Probe::url(Op::Archive, None)?;
+        self.pages(&path)
+    }
+
+    /// This is the `measurements` method, returning all the measurements a given probe
+    /// is participating in.  Pages are fetched as needed, see `MeasurementFilter` for the
+    /// available filters.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::measurements::{Measurement, MeasurementFilter};
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// for m in c.probe().with(MeasurementFilter::new().mtype("dns")).measurements(666).unwrap() {
+    ///     println!("{}", m.unwrap().id);
+    /// }
+    /// ```
+    ///
+    pub fn measurements(self, pn: impl Into<ProbeId>) -> Result<Pages<Measurement>, APIError> {
+        let path = Probe::url(Op::Measurement, Some(&pn.into()))?;
+        self.pages(&path)
+    }
+
+    /// This is the `rankings` method, returning the probe rankings.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::Ranking;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let r: Vec<Ranking> = c.probe().rankings().unwrap();
+    /// ```
+    ///
+    pub fn rankings(self) -> Result<Vec<Ranking>, APIError> {
+        let path = Probe::url(Op::Rankings, None)?;
+        self.pages(&path)?.collect()
+    }
+
+    /// This is the `tags` method, returning the global tag catalogue with usage counts.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::TagInfo;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let t: Vec<TagInfo> = c.probe().tags().unwrap();
+    /// ```
+    ///
+    pub fn tags(self) -> Result<Vec<TagInfo>, APIError> {
+        let path = Probe::url(Op::Tags, None)?;
+        self.pages(&path)?.collect()
+    }
+
+    /// This is the `slugs` method, returning the slugs matching a given tag.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::probes::Tag;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let t: Vec<Tag> = c.probe().slugs("system-ipv6-works").unwrap();
+    /// ```
+    ///
+    pub fn slugs(self, tag: &str) -> Result<Vec<Tag>, APIError> {
+        let path = Probe::slugs_url(tag)?;
+        self.pages(&path)?.collect()
+    }
+}
+
+/// Calls specific to measurements
+///
+impl RequestBuilder<Measurement> {
+    /// This is the `results` method, returning all the results of a measurement.  Use
+    /// `with()` to give `start`, `stop` or `probe_ids`.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::results::MeasurementResult;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let r: Vec<MeasurementResult> = c.measurement().results(1001).unwrap();
+    /// ```
+    ///
+    pub fn results(
+        self,
+        msm: impl Into<MeasurementId>,
+    ) -> Result<Vec<MeasurementResult>, APIError> {
+        let path = Measurement::url(Op::Results, Some(&msm.into()))?;
+        self.fetch(&path)
+    }
+
+    /// This is the streaming version of `results`, asking for the NDJSON format (`format=txt`)
+    /// and decoding results one line at a time as they are read from the connection.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// for r in c.measurement().results_reader(1001).unwrap() {
+    ///     println!("{}", r.unwrap());
+    /// }
+    /// ```
+    ///
+    pub fn results_reader(
+        mut self,
+        msm: impl Into<MeasurementId>,
+    ) -> Result<ResultReader<Source>, APIError> {
+        let path = Measurement::url(Op::Results, Some(&msm.into()))?;
+        self.c.opts["format"] = "txt".to_string();
+        let url = self.url_for(&path)?;
+
+        let resp = self.call(Method::Get, url, None)?;
+
+        if resp.is_success() {
+            ResultReader::from_reader(resp.body)
+        } else {
+            Err(resp.json::<APIError>()?)
+        }
+    }
+
+    /// This is the `latest` method, returning only the most recent result of each probe.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::results::MeasurementResult;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let r: Vec<MeasurementResult> = c.measurement().latest(1001).unwrap();
+    /// ```
+    ///
+    pub fn latest(self, msm: impl Into<MeasurementId>) -> Result<Vec<MeasurementResult>, APIError> {
+        let path = Measurement::url(Op::Latest, Some(&msm.into()))?;
+        self.fetch(&path)
+    }
+
+    /// This is the `status_check` method, only for ping measurements.  It returns the
+    /// probes which are alerting on packet loss or RTT, see `StatusCheckFilter` for the
+    /// thresholds.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::results::{StatusCheck, StatusCheckFilter};
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let s: StatusCheck = c.measurement()
+    ///     .with(StatusCheckFilter::new().max_packet_loss(50))
+    ///     .status_check(1001)
+    ///     .unwrap();
+    /// println!("alerting: {:?}", s.alerting());
+    /// ```
+    ///
+    pub fn status_check(self, msm: impl Into<MeasurementId>) -> Result<StatusCheck, APIError> {
+        let path = Measurement::url(Op::StatusCheck, Some(&msm.into()))?;
+        self.fetch(&path)
+    }
 }
 
-/// Take an url and a set of options to add to the parameters
+/// Calls specific to credits
+///
+impl RequestBuilder<Credits> {
+    /// This is the `info` method close to `get` but without a parameter.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::credits::Credits;
+    ///
+    /// let mut c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let res: Credits = c.credits().info().unwrap()
+    /// # ;
+    /// ```
+    ///
+    pub fn info(self) -> Result<Credits, APIError> {
+        let path = Credits::url(Op::Info, None)?;
+        self.fetch(&path)
+    }
+
+    /// This is the `expenses` method, returning the credits expense items.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let e = c.credits().expenses().unwrap();
+    /// println!("{} credits/day", e.total_estimated_daily_expenditure);
+    /// ```
+    ///
+    pub fn expenses(self) -> Result<ExpenseItems, APIError> {
+        let path = Credits::url(Op::Expenses, None)?;
+        self.fetch(&path)
+    }
+}
+
+/// Calls specific to participation requests, they live under the measurement they apply to
+///
+impl RequestBuilder<ParticipationRequests> {
+    /// This is the `list_for` method, returning all the participation requests of a
+    /// measurement.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::participation_requests::ParticipationRequests;
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let res: Vec<ParticipationRequests> = c.participation_requests().list_for(1001).unwrap();
+    /// ```
+    ///
+    pub fn list_for(
+        self,
+        msm: impl Into<MeasurementId>,
+    ) -> Result<Vec<ParticipationRequests>, APIError> {
+        let path = ParticipationRequests::measurement_url(&msm.into());
+        self.pages(&path)?.collect()
+    }
+
+    /// This is the `create_for` method, `POST`-ing new participation requests to add
+    /// probes to or remove probes from a running measurement.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use atlas_rs::client::ClientBuilder;
+    /// # use atlas_rs::core::participation_requests::{ParticipationChange, RequestIds};
+    ///
+    /// let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+    ///
+    /// let changes = vec![ParticipationChange::add_country(5, "FR")];
+    /// let res: RequestIds = c.participation_requests().create_for(1001, &changes).unwrap();
+    /// ```
+    ///
+    pub fn create_for(
+        self,
+        msm: impl Into<MeasurementId>,
+        changes: &[ParticipationChange],
+    ) -> Result<RequestIds, APIError> {
+        let path = ParticipationRequests::measurement_url(&msm.into());
+        self.send_json(Method::Post, &path, &changes)
+    }
+}
+
+/// Take an url and a set of options to add to the parameters, percent-encoded and in the
+/// order they were given
 ///
 /// Example!
 /// ```no_run
@@ -310,43 +748,95 @@ impl RequestBuilder {
 /// ```
 ///
 pub fn add_opts(url: &str, opts: &Options) -> String {
-    let full = url.to_owned() + "?";
-    let mut v = Vec::<String>::new();
-
-    for name in opts.keys().sorted() {
-        let opt = format!("{}={}", name, opts[name]);
-        v.push(opt);
+    if opts.is_empty() {
+        return url.to_owned();
     }
-    full + &v.join("&")
+    format!("{}?{}", url, opts.to_query())
 }
 
 #[cfg(test)]
 mod tests {
-    use reqwest::blocking::Request;
     use reqwest::Url;
 
+    use crate::client::ClientBuilder;
     use crate::option::Options;
 
     use super::*;
 
     #[test]
     fn test_requestbuilder_new() {
-        let ctx = Ctx::None;
         let cl = Client::new();
         let url = Url::parse("http://localhost/").unwrap();
-        let rq = Request::new(reqwest::Method::GET, url);
-        let r = RequestBuilder::new(ctx, cl, rq);
+        let rq = HttpRequest::get(url);
+        let r = RequestBuilder::<Probe>::new(cl, rq);
 
         assert!(!r.paged);
-        assert_eq!(reqwest::Method::GET, r.r.method());
+        assert_eq!(Method::Get, r.r.method);
+    }
+
+    #[test]
+    fn test_bad_op() {
+        // Nothing is sent, the cassette does not even exist
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .replay("/nonexistent")
+            .build()
+            .unwrap();
+
+        let e = c.probe().delete(666).unwrap_err();
+        assert_eq!("Bad operation", e.error.title);
+    }
+
+    #[test]
+    fn test_op_urls() {
+        assert_eq!(
+            "/probes/666/measurements/",
+            Probe::url(Op::Measurement, Some(&ProbeId(666))).unwrap()
+        );
+        assert_eq!(
+            "/measurements/1001/status-check/",
+            Measurement::url(Op::StatusCheck, Some(&MeasurementId(1001))).unwrap()
+        );
+        assert_eq!("/probes/rankings/", Probe::url(Op::Rankings, None).unwrap());
+        assert_eq!(
+            "/credits/members/claim/",
+            Credits::url(Op::Claim, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fields_include_sort() {
+        let c = ClientBuilder::new().api_key("FOO").build().unwrap();
+        let r = c
+            .probe()
+            .fields(&["id", "status"])
+            .optional_fields(&[])
+            .include(&["tags"])
+            .sort("-id");
+
+        assert_eq!("id,status", r.c.opts["fields"]);
+        assert!(!r.c.opts.contains_key("optional_fields"));
+        assert_eq!("tags", r.c.opts["include"]);
+        assert_eq!("-id", r.c.opts["sort"]);
+
+        let url = r.url_for("").unwrap();
+        assert!(url.as_str().contains("fields=id%2Cstatus"));
     }
 
     #[test]
     fn test_add_opts() {
         let url = "/hello".to_string();
-        let o = Options::from([("name", "foo"), ("bar", "baz")]);
-
-        let url = add_opts(&url, &o);
-        assert_eq!("/hello?bar=baz&name=foo", url);
+        let o = Options::from([
+            ("name", "foo bar"),
+            ("id__in", "1,2"),
+            ("tags", "a"),
+            ("tags", "b"),
+        ]);
+
+        assert_eq!(
+            "/hello?name=foo+bar&id__in=1%2C2&tags=a&tags=b",
+            add_opts(&url, &o)
+        );
+        assert_eq!("/hello", add_opts(&url, &Options::new()));
     }
 }
@@ -0,0 +1,511 @@
+//! Module to receive measurement results in real-time from the RIPE Atlas result stream.
+//!
+//! The stream is a WebSocket where we send one or more subscriptions (by measurement,
+//! probe, type, prefix or source AS) and then receive every matching result as soon as it
+//! is published.  Messages are JSON arrays with the event name first and its payload:
+//!
+//! ```text
+//! -> ["atlas_subscribe", {"streamType": "result", "msm": 1001}]
+//! <- ["atlas_subscribed", {...}]
+//! <- ["atlas_result", {...}]
+//! ```
+//!
+//! `Stream` is an iterator over the typed results.  If the connection drops, it reconnects
+//! and subscribes again, giving up after too many consecutive failures.  A connection silent
+//! for too long is pinged and considered dead if it stays silent.
+//!
+//! Example:
+//! ```no_run
+//! use atlas_rs::stream::{Stream, Subscription};
+//!
+//! let s = Stream::new().subscribe(Subscription::new().msm(1001));
+//!
+//! for r in s.take(10) {
+//!     println!("{}", r.unwrap());
+//! }
+//! ```
+//!
+//! The URL can be changed with `url()`, which is how the tests use a local server.
+//!
+
+// Standard library
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// External crates
+use log::trace;
+use serde::Serialize;
+use serde_json::Value;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+// Our crates
+use crate::core::results::MeasurementResult;
+use crate::errors::APIError;
+use crate::ids::{MeasurementId, ProbeId};
+use crate::kinds::MeasurementType;
+
+// ---------------------------------------------------------------------------
+
+/// Default URL of the result stream
+const STREAM_URL: &str = "wss://atlas-stream.ripe.net/stream/";
+
+/// How many times in a row do we try to reconnect
+const RETRIES: u32 = 5;
+
+/// Base delay between reconnections, multiplied by the attempt number
+const BACKOFF: Duration = Duration::from_secs(1);
+
+/// Silence before we ping the server, and then before we give up on the connection
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+// ---------------------------------------------------------------------------
+
+/// One subscription to the result stream, only the set filters are sent.
+///
+/// Example:
+/// ```
+/// use atlas_rs::stream::Subscription;
+///
+/// let s = Subscription::new().mtype("ping").source_asn(3215);
+/// ```
+///
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct Subscription {
+    /// Always `result` for now
+    #[serde(rename = "streamType")]
+    stream_type: String,
+    /// Measurement ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    msm: Option<MeasurementId>,
+    /// Probe ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prb: Option<ProbeId>,
+    /// Measurement type
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    mtype: Option<MeasurementType>,
+    /// Prefix of the probe address
+    #[serde(rename = "sourcePrefix", skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+    /// AS of the probe
+    #[serde(rename = "sourceAsn", skip_serializing_if = "Option::is_none")]
+    asn: Option<u32>,
+    /// Also send the results of the last few minutes
+    #[serde(rename = "sendBacklog", skip_serializing_if = "Option::is_none")]
+    backlog: Option<bool>,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Subscription::new()
+    }
+}
+
+impl Subscription {
+    /// Subscribe to all results, add filters to narrow it
+    ///
+    pub fn new() -> Self {
+        Subscription {
+            stream_type: "result".to_string(),
+            msm: None,
+            prb: None,
+            mtype: None,
+            prefix: None,
+            asn: None,
+            backlog: None,
+        }
+    }
+
+    /// Only results of this measurement
+    ///
+    pub fn msm(mut self, id: impl Into<MeasurementId>) -> Self {
+        self.msm = Some(id.into());
+        self
+    }
+
+    /// Only results from this probe
+    ///
+    pub fn probe(mut self, id: impl Into<ProbeId>) -> Self {
+        self.prb = Some(id.into());
+        self
+    }
+
+    /// Only results of this type (ping, traceroute, etc.)
+    ///
+    pub fn mtype(mut self, mtype: impl Into<MeasurementType>) -> Self {
+        self.mtype = Some(mtype.into());
+        self
+    }
+
+    /// Only results from probes within this prefix
+    ///
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Only results from probes in this AS
+    ///
+    pub fn source_asn(mut self, asn: u32) -> Self {
+        self.asn = Some(asn);
+        self
+    }
+
+    /// Start with the recent results
+    ///
+    pub fn backlog(mut self, b: bool) -> Self {
+        self.backlog = Some(b);
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Connection to the result stream, use it as an iterator or get a channel with `spawn()`.
+///
+#[derive(Debug)]
+pub struct Stream {
+    /// URL of the stream
+    url: String,
+    /// What we want
+    subs: Vec<Subscription>,
+    /// How many consecutive reconnections we allow
+    retries: u32,
+    /// Base delay between reconnections
+    backoff: Duration,
+    /// How long we wait for a message
+    timeout: Duration,
+    /// Current failed attempts
+    failures: u32,
+    /// Current connection, if any
+    ws: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Stream::new()
+    }
+}
+
+impl Stream {
+    /// Setup the stream with the defaults, nothing is connected until the first result
+    /// is asked for.
+    ///
+    pub fn new() -> Self {
+        Stream {
+            url: STREAM_URL.to_string(),
+            subs: vec![],
+            retries: RETRIES,
+            backoff: BACKOFF,
+            timeout: READ_TIMEOUT,
+            failures: 0,
+            ws: None,
+        }
+    }
+
+    /// Use another stream URL
+    ///
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Add a subscription
+    ///
+    pub fn subscribe(mut self, s: Subscription) -> Self {
+        self.subs.push(s);
+        self
+    }
+
+    /// Set how many times in a row we try to reconnect before giving up
+    ///
+    pub fn retries(mut self, n: u32) -> Self {
+        self.retries = n;
+        self
+    }
+
+    /// Set the base delay between reconnections
+    ///
+    pub fn backoff(mut self, d: Duration) -> Self {
+        self.backoff = d;
+        self
+    }
+
+    /// Set how long the connection can be silent before we ping the server, and then before
+    /// we reconnect
+    ///
+    pub fn timeout(mut self, d: Duration) -> Self {
+        self.timeout = d;
+        self
+    }
+
+    /// Run the stream in its own thread and send the results through a channel.  The
+    /// thread stops when the receiver is dropped or the stream gives up.
+    ///
+    /// Example:
+    /// ```no_run
+    /// use atlas_rs::stream::{Stream, Subscription};
+    ///
+    /// let rx = Stream::new().subscribe(Subscription::new().msm(1001)).spawn();
+    ///
+    /// while let Ok(r) = rx.recv() {
+    ///     println!("{}", r.unwrap());
+    /// }
+    /// ```
+    ///
+    pub fn spawn(self) -> mpsc::Receiver<Result<MeasurementResult, APIError>> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for r in self {
+                if tx.send(r).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    /// Connect and send all subscriptions
+    ///
+    fn connect(&mut self) -> Result<(), APIError> {
+        let (mut ws, _) = tungstenite::connect(self.url.as_str())?;
+        let timeout = Some(self.timeout).filter(|t| !t.is_zero());
+        match ws.get_mut() {
+            MaybeTlsStream::Plain(s) => s.set_read_timeout(timeout)?,
+            MaybeTlsStream::NativeTls(s) => s.get_mut().set_read_timeout(timeout)?,
+            _ => (),
+        }
+        for s in &self.subs {
+            let msg = serde_json::to_string(&("atlas_subscribe", s))?;
+            ws.write_message(Message::Text(msg))?;
+        }
+        self.ws = Some(ws);
+        Ok(())
+    }
+
+    /// Read until we get a result, `Ok(None)` means the connection was closed.
+    ///
+    fn read_result(&mut self) -> Result<Option<MeasurementResult>, ReadError> {
+        let ws = match self.ws.as_mut() {
+            Some(ws) => ws,
+            None => return Ok(None),
+        };
+        let mut pinged = false;
+        loop {
+            let msg = match ws.read_message() {
+                Ok(m) => m,
+                // Silent for too long, is anybody there?
+                Err(tungstenite::Error::Io(e))
+                    if !pinged
+                        && matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    ws.write_message(Message::Ping(vec![]))?;
+                    pinged = true;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            pinged = false;
+            let txt = match msg {
+                Message::Text(t) => t,
+                Message::Close(_) => return Ok(None),
+                // Pings are answered by tungstenite itself
+                _ => continue,
+            };
+
+            let (event, payload): (String, Value) = serde_json::from_str(&txt)?;
+            match event.as_str() {
+                "atlas_result" => return Ok(Some(serde_json::from_value(payload)?)),
+                "atlas_error" => {
+                    return Err(ReadError::Api(APIError::new(
+                        400,
+                        "Stream error",
+                        &payload.to_string(),
+                        "stream",
+                    )))
+                }
+                // atlas_subscribed, atlas_metadata, etc.
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl Iterator for Stream {
+    type Item = Result<MeasurementResult, APIError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.ws.is_none() {
+                if self.failures > self.retries {
+                    return None;
+                }
+                if self.failures > 0 {
+                    thread::sleep(self.backoff * self.failures);
+                }
+                if let Err(e) = self.connect() {
+                    self.failures += 1;
+                    if self.failures > self.retries {
+                        return Some(Err(e));
+                    }
+                    continue;
+                }
+            }
+
+            match self.read_result() {
+                Ok(Some(r)) => {
+                    self.failures = 0;
+                    return Some(Ok(r));
+                }
+                // Decoding or API errors do not break the connection
+                Err(ReadError::Api(e)) => return Some(Err(e)),
+                // Connection dropped, reconnect
+                res => {
+                    if let Err(ReadError::Socket(e)) = res {
+                        trace!("stream: {}, reconnecting", e);
+                    }
+                    self.ws = None;
+                    self.failures += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Why reading failed: the connection, after which we reconnect, or what we got from it,
+/// given to the caller.
+///
+#[derive(Debug)]
+enum ReadError {
+    Socket(Box<tungstenite::Error>),
+    Api(APIError),
+}
+
+impl From<tungstenite::Error> for ReadError {
+    fn from(e: tungstenite::Error) -> Self {
+        ReadError::Socket(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for ReadError {
+    fn from(e: serde_json::Error) -> Self {
+        ReadError::Api(e.into())
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const RESULT: &str = r#"{"fw":5020,"msm_id":1001,"prb_id":666,"timestamp":1652000000,
+        "type":"ping","avg":9.3,"sent":1,"rcvd":1,"result":[{"rtt":9.3}]}"#;
+
+    /// Stand-in for the Atlas stream: each connection gets the given number of results
+    /// before being closed.
+    ///
+    fn server(sessions: Vec<usize>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/stream/", listener.local_addr().unwrap());
+
+        let h = thread::spawn(move || {
+            let mut subs = vec![];
+            for n in sessions {
+                let (sock, _) = listener.accept().unwrap();
+                let mut ws = tungstenite::accept(sock).unwrap();
+
+                match ws.read_message().unwrap() {
+                    Message::Text(t) => subs.push(t),
+                    m => panic!("unexpected {:?}", m),
+                }
+                ws.write_message(Message::Text(r#"["atlas_subscribed",{}]"#.to_string()))
+                    .unwrap();
+                for _ in 0..n {
+                    let msg = format!(r#"["atlas_result",{}]"#, RESULT);
+                    ws.write_message(Message::Text(msg)).unwrap();
+                }
+                let _ = ws.close(None);
+                let _ = ws.write_pending();
+            }
+            subs
+        });
+        (url, h)
+    }
+
+    #[test]
+    fn test_subscription() {
+        let s = Subscription::new().msm(1001).mtype("ping");
+
+        assert_eq!(
+            r#"["atlas_subscribe",{"streamType":"result","msm":1001,"type":"ping"}]"#,
+            serde_json::to_string(&("atlas_subscribe", &s)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stream_reconnect() {
+        let (url, h) = server(vec![2, 1]);
+
+        let s = Stream::new()
+            .url(&url)
+            .backoff(Duration::from_millis(10))
+            .subscribe(Subscription::new().msm(1001));
+
+        let res: Vec<MeasurementResult> = s.take(3).map(|r| r.unwrap()).collect();
+        let subs = h.join().unwrap();
+
+        assert_eq!(3, res.len());
+        assert!(res.iter().all(|r| r.prb_id() == 666));
+        // We subscribed again after the first connection was closed
+        assert_eq!(2, subs.len());
+        assert!(subs[1].contains(r#""msm":1001"#));
+    }
+
+    #[test]
+    fn test_stream_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/stream/", listener.local_addr().unwrap());
+
+        // Accepts, then never says anything nor answers pings
+        let (done, wait) = mpsc::channel::<()>();
+        let h = thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            let _ws = tungstenite::accept(sock).unwrap();
+            let _ = wait.recv();
+        });
+
+        let mut s = Stream::new()
+            .url(&url)
+            .retries(0)
+            .timeout(Duration::from_millis(50))
+            .subscribe(Subscription::new().msm(1001));
+
+        assert!(s.next().is_none());
+        drop(done);
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn test_stream_give_up() {
+        let (url, h) = server(vec![1]);
+
+        let rx = Stream::new()
+            .url(&url)
+            .retries(1)
+            .backoff(Duration::from_millis(10))
+            .subscribe(Subscription::new().msm(1001))
+            .spawn();
+
+        assert!(rx.recv().unwrap().is_ok());
+        h.join().unwrap();
+        // Server is gone, we get the connection error and the channel is closed
+        assert!(rx.recv().unwrap().is_err());
+        assert!(rx.recv().is_err());
+    }
+}
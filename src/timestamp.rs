@@ -0,0 +1,397 @@
+//! Module defining the `Timestamp` type used for all dates in the API objects.
+//!
+//! The API is not consistent: some dates are POSIX times (`first_connected`, `start_time`)
+//! and others are ISO 8601 strings with or without a timezone (`created_at`, `date_live`).
+//! Both are accepted when decoding and stored as seconds since the epoch, UTC.  They are
+//! displayed as `YYYY-MM-DD HH:MM:SS UTC` and serialized back as POSIX times.
+//!
+//! With the `chrono` feature (on by default), parsing and formatting are done by `chrono` and
+//! `Timestamp` converts to and from `DateTime<Utc>`.  Without it, a small built-in parser
+//! accepting the same formats is used.
+//!
+//! Example:
+//! ```
+//! use atlas_rs::timestamp::Timestamp;
+//!
+//! let t: Timestamp = "2022-05-01T12:00:00+02:00".parse().unwrap();
+//!
+//! assert_eq!(1651399200, t.secs());
+//! assert_eq!("2022-05-01 10:00:00 UTC", t.to_string());
+//! assert_eq!(t, "1651399200".parse::<Timestamp>().unwrap());
+//! ```
+//!
+
+// Standard library
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// External crates
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Our crates
+use crate::errors::APIError;
+
+// ---------------------------------------------------------------------------
+
+/// A date, in seconds since the epoch (UTC)
+///
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    /// Current time
+    ///
+    pub fn now() -> Self {
+        Timestamp::from(SystemTime::now())
+    }
+
+    /// Seconds since the epoch
+    ///
+    pub fn secs(&self) -> i64 {
+        self.0
+    }
+
+    /// Time elapsed since then, zero if in the future
+    ///
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs((Timestamp::now().0 - self.0).max(0) as u64)
+    }
+}
+
+/// Human form, always in UTC
+///
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match format_utc(self.0) {
+            Some(t) => f.pad(&t),
+            None => f.pad(&self.0.to_string()),
+        }
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(v: i64) -> Self {
+        Timestamp(v)
+    }
+}
+
+impl From<Timestamp> for i64 {
+    fn from(t: Timestamp) -> Self {
+        t.0
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(t: SystemTime) -> Self {
+        match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => Timestamp(d.as_secs() as i64),
+            Err(e) => Timestamp(-(e.duration().as_secs() as i64)),
+        }
+    }
+}
+
+impl PartialEq<i64> for Timestamp {
+    fn eq(&self, other: &i64) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Either a POSIX time or an ISO 8601 date (`2022-05-01`, `2022-05-01T12:00:00Z`,
+/// `2022-05-01 12:00:00.123+02:00`, etc.), UTC if no timezone is given.
+///
+impl FromStr for Timestamp {
+    type Err = APIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(v) = s.parse::<i64>() {
+            return Ok(Timestamp(v));
+        }
+        parse_iso8601(s).map(Timestamp).ok_or_else(|| {
+            APIError::new(
+                400,
+                "Bad timestamp",
+                &format!("'{}' is neither a POSIX time nor an ISO 8601 date", s),
+                "timestamp",
+            )
+        })
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+/// Accept numbers and strings
+///
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("a POSIX time or an ISO 8601 date")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Timestamp(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i64::try_from(v).map(Timestamp).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Timestamp(v as i64))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(|e: APIError| E::custom(e.error.detail))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(t: DateTime<Utc>) -> Self {
+        Timestamp(t.timestamp())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for DateTime<Utc> {
+    fn from(t: Timestamp) -> Self {
+        DateTime::from_timestamp(t.0, 0).unwrap_or_default()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+///
+#[cfg(feature = "chrono")]
+fn format_utc(secs: i64) -> Option<String> {
+    DateTime::from_timestamp(secs, 0).map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+}
+
+/// Formats with a timezone (`Z`, `+HH`, `+HHMM` or `+HH:MM`)
+#[cfg(feature = "chrono")]
+const WITH_TZ: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f%#z", "%Y-%m-%dT%H:%M%#z"];
+
+/// Formats without a timezone, taken as UTC
+#[cfg(feature = "chrono")]
+const WITHOUT_TZ: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"];
+
+/// Parse `YYYY-MM-DD[(T| )HH:MM[:SS[.frac]]][Z|(+|-)HH[:]MM]`, fractions are dropped
+///
+#[cfg(feature = "chrono")]
+fn parse_iso8601(s: &str) -> Option<i64> {
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(d.and_hms_opt(0, 0, 0)?.and_utc().timestamp());
+    }
+
+    let s = s.replacen([' ', 't'], "T", 1);
+    WITH_TZ
+        .iter()
+        .find_map(|f| DateTime::parse_from_str(&s, f).ok())
+        .map(|t| t.timestamp())
+        .or_else(|| {
+            WITHOUT_TZ
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(&s, f).ok())
+                .map(|t| t.and_utc().timestamp())
+        })
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+///
+#[cfg(not(feature = "chrono"))]
+fn format_utc(secs: i64) -> Option<String> {
+    let (y, m, d) = civil_from_days(secs.div_euclid(86400));
+    let secs = secs.rem_euclid(86400);
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        y,
+        m,
+        d,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    ))
+}
+
+/// Parse `YYYY-MM-DD[(T| )HH:MM[:SS[.frac]]][Z|(+|-)HH[:]MM]`, fractions are dropped
+///
+#[cfg(not(feature = "chrono"))]
+fn parse_iso8601(s: &str) -> Option<i64> {
+    let num = |s: &str| -> Option<i64> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+
+    let (date, rest) = match s.find(['T', 't', ' ']) {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    };
+
+    let mut d = date.splitn(3, '-');
+    let (y, m, dd) = (num(d.next()?)?, num(d.next()?)?, num(d.next()?)?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&dd) {
+        return None;
+    }
+    let days = days_from_civil(y, m, dd);
+
+    // Timezone, if any
+    let (time, offset) = if let Some(t) = rest.strip_suffix(['Z', 'z']) {
+        (t, 0)
+    } else if let Some(i) = rest.rfind(['+', '-']) {
+        let (t, tz) = rest.split_at(i);
+        let sign = if tz.starts_with('-') { -1 } else { 1 };
+        let tz = tz[1..].replace(':', "");
+        let (h, mi) = match tz.len() {
+            2 => (num(&tz)?, 0),
+            4 => (num(&tz[..2])?, num(&tz[2..])?),
+            _ => return None,
+        };
+        (t, sign * (h * 3600 + mi * 60))
+    } else {
+        (rest, 0)
+    };
+
+    let secs = if time.is_empty() {
+        0
+    } else {
+        let time = time.split('.').next()?;
+        let mut t = time.split(':');
+        let h = num(t.next()?)?;
+        let mi = num(t.next()?)?;
+        let sec = match t.next() {
+            Some(v) => num(v)?,
+            None => 0,
+        };
+        if h > 23 || mi > 59 || sec > 60 {
+            return None;
+        }
+        h * 3600 + mi * 60 + sec
+    };
+
+    Some(days * 86400 + secs - offset)
+}
+
+/// Days since 1970-01-01 for a given date (proleptic Gregorian calendar)
+///
+#[cfg(not(feature = "chrono"))]
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date for a given number of days since 1970-01-01
+///
+#[cfg(not(feature = "chrono"))]
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("1652000000", 1652000000)]
+    #[case("1970-01-01", 0)]
+    #[case("2020-01-01", 1577836800)]
+    #[case("2022-05-01T00:00:00Z", 1651363200)]
+    #[case("2022-05-01T12:00:00+02:00", 1651399200)]
+    #[case("2022-05-01 12:00:00-0130", 1651411800)]
+    #[case("2015-07-01T08:16:14.183542", 1435738574)]
+    #[case("2000-02-29T23:59", 951868740)]
+    fn test_parse_ok(#[case] s: &str, #[case] secs: i64) {
+        assert_eq!(Timestamp(secs), s.parse::<Timestamp>().unwrap());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("yesterday")]
+    #[case("2022-13-01")]
+    #[case("2022-05-01T25:00:00Z")]
+    #[case("2022-05-01T12:00:00+2")]
+    fn test_parse_nok(#[case] s: &str) {
+        assert!(s.parse::<Timestamp>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("1970-01-01 00:00:00 UTC", Timestamp(0).to_string());
+        assert_eq!("2022-05-08 12:00:00 UTC", Timestamp(1652011200).to_string());
+        assert_eq!("1969-12-31 23:59:59 UTC", Timestamp(-1).to_string());
+        assert_eq!(
+            "Some(Timestamp(1652011200))",
+            format!("{:?}", Some(Timestamp(1652011200)))
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        #[derive(Deserialize, Serialize)]
+        struct T {
+            a: Timestamp,
+            b: Option<Timestamp>,
+            c: Option<Timestamp>,
+        }
+
+        let t: T = serde_json::from_str(r#"{"a":1652011200,"b":"2022-05-08T12:00:00Z","c":null}"#)
+            .unwrap();
+        assert_eq!(t.a, t.b.unwrap());
+        assert!(t.c.is_none());
+        assert_eq!(
+            r#"{"a":1652011200,"b":1652011200,"c":null}"#,
+            serde_json::to_string(&t).unwrap()
+        );
+
+        let t: Result<T, _> = serde_json::from_str(r#"{"a":"soon","b":null,"c":null}"#);
+        assert!(t.is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let t = Timestamp(1652011200);
+        let dt: DateTime<Utc> = t.into();
+
+        assert_eq!("2022-05-08T12:00:00+00:00", dt.to_rfc3339());
+        assert_eq!(t, Timestamp::from(dt));
+    }
+}
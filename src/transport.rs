@@ -0,0 +1,587 @@
+//! Module defining how HTTP requests are actually sent.
+//!
+//! `Client` never talks to `reqwest` directly, every call goes through the `Transport` trait
+//! with our own small request and response types.  The default is `ReqwestTransport` but
+//! anything implementing the trait can be given to `ClientBuilder::transport()`, like a
+//! recorded-fixture transport for tests, a corporate HTTP stack or an instrumented one.
+//!
+//! Example:
+//! ```no_run
+//! use atlas_rs::client::ClientBuilder;
+//! use atlas_rs::errors::APIError;
+//! use atlas_rs::transport::{HttpRequest, HttpResponse, ReqwestTransport, Transport};
+//!
+//! /// Count every call before sending it
+//! #[derive(Debug, Default)]
+//! struct Counting {
+//!     calls: std::sync::atomic::AtomicUsize,
+//!     inner: ReqwestTransport,
+//! }
+//!
+//! impl Transport for Counting {
+//!     fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+//!         self.calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+//!         self.inner.send(req)
+//!     }
+//! }
+//!
+//! let c = ClientBuilder::new()
+//!     .api_key("FOO")
+//!     .transport(Counting::default())
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+
+// Standard library
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// External crates
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+// Our crates
+use crate::errors::APIError;
+
+// ---------------------------------------------------------------------------
+
+/// What replaces secrets when displayed or saved
+pub const REDACTED: &str = "REDACTED";
+
+/// Headers never displayed
+const SECRET_HEADERS: [&str; 1] = ["authorization"];
+
+/// Query parameters never displayed
+pub(crate) const SECRET_PARAMS: [&str; 1] = ["key"];
+
+/// Path elements followed by a secret (API keys are their own ID)
+pub(crate) const SECRET_PATHS: [&str; 1] = ["keys"];
+
+/// Default time to establish the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time for the whole request, none so that downloads of large results can take as
+/// long as they need
+const TIMEOUT: Duration = Duration::ZERO;
+
+// ---------------------------------------------------------------------------
+
+/// HTTP methods used by the API
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Delete,
+    Get,
+    Patch,
+    Post,
+    Put,
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let m = match self {
+            Method::Delete => "DELETE",
+            Method::Get => "GET",
+            Method::Patch => "PATCH",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+        };
+        write!(f, "{}", m)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// One request to send
+///
+#[derive(Clone, PartialEq)]
+pub struct HttpRequest {
+    /// HTTP method
+    pub method: Method,
+    /// Full URL including the query
+    pub url: Url,
+    /// Extra headers
+    pub headers: Vec<(String, String)>,
+    /// Body, always JSON when present
+    pub body: Option<Vec<u8>>,
+}
+
+/// Credentials are not displayed
+///
+impl Debug for HttpRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(k, v)| {
+                if SECRET_HEADERS.contains(&k.to_ascii_lowercase().as_str()) {
+                    (k.as_str(), REDACTED)
+                } else {
+                    (k.as_str(), v.as_str())
+                }
+            })
+            .collect();
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &redacted(&self.url))
+            .field("headers", &headers)
+            .field("body", &self.body.as_ref().map(|b| b.len()))
+            .finish()
+    }
+}
+
+impl HttpRequest {
+    /// Request without a body
+    ///
+    pub fn new(method: Method, url: Url) -> Self {
+        HttpRequest {
+            method,
+            url,
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    /// Shortcut for `GET`
+    ///
+    pub fn get(url: Url) -> Self {
+        HttpRequest::new(Method::Get, url)
+    }
+
+    /// Add a header
+    ///
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set a JSON body
+    ///
+    pub fn json(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self.header("content-type", "application/json")
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Answer from the transport, the body is read only when needed
+///
+pub struct HttpResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Headers, names are lowercase
+    pub headers: Vec<(String, String)>,
+    /// Body
+    pub body: Box<dyn Read + Send>,
+}
+
+impl Debug for HttpResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+impl HttpResponse {
+    /// Response with a body already in memory
+    ///
+    pub fn new(status: u16, body: Vec<u8>) -> Self {
+        HttpResponse {
+            status,
+            headers: vec![],
+            body: Box::new(Cursor::new(body)),
+        }
+    }
+
+    /// Is it a 2xx?
+    ///
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Value of the given header, if present
+    ///
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Read the whole body as a string
+    ///
+    pub fn text(mut self) -> Result<String, APIError> {
+        let mut s = String::new();
+        self.body.read_to_string(&mut s)?;
+        Ok(s)
+    }
+
+    /// Decode the whole body as JSON
+    ///
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, APIError> {
+        Ok(serde_json::from_reader(self.body)?)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Path and query of `url` safe to be logged: key IDs and `key` parameters are replaced by
+/// `REDACTED`.
+///
+/// Example:
+///
+/// ```
+/// # use reqwest::Url;
+/// # use atlas_rs::transport::redacted;
+///
+/// let url = Url::parse("https://atlas.ripe.net/api/v2/keys/1234/?key=FOO").unwrap();
+/// assert_eq!("/api/v2/keys/REDACTED/?key=REDACTED", redacted(&url));
+/// ```
+///
+pub fn redacted(url: &Url) -> String {
+    let path = redacted_path(url.path());
+
+    match url.query() {
+        None => path,
+        Some(_) => {
+            let query: Vec<String> = url
+                .query_pairs()
+                .map(|(k, v)| {
+                    if SECRET_PARAMS.contains(&k.as_ref()) {
+                        format!("{}={}", k, REDACTED)
+                    } else {
+                        format!("{}={}", k, v)
+                    }
+                })
+                .collect();
+            format!("{}?{}", path, query.join("&"))
+        }
+    }
+}
+
+/// Hide the element following a secret one in `path`
+///
+pub(crate) fn redacted_path(path: &str) -> String {
+    let mut hide = false;
+    let path: Vec<&str> = path
+        .split('/')
+        .map(|s| {
+            let s = if hide && !s.is_empty() { REDACTED } else { s };
+            hide = SECRET_PATHS.contains(&s);
+            s
+        })
+        .collect();
+    path.join("/")
+}
+
+// ---------------------------------------------------------------------------
+
+/// What is needed to send requests on behalf of a `Client`
+///
+pub trait Transport: Debug + Send + Sync {
+    /// Send the request and return the answer, whatever its status.  Only errors at the
+    /// transport level (DNS, connection, etc.) are returned as `Err`.
+    ///
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError>;
+}
+
+/// Settings of the default transport
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpConfig {
+    /// Time to establish the connection
+    pub connect_timeout: Duration,
+    /// Time for the whole request including the body, zero means no limit
+    pub timeout: Duration,
+    /// HTTP, HTTPS or SOCKS5 proxy URL, otherwise the usual environment variables are used
+    pub proxy: Option<String>,
+    /// PEM file with extra CA certificates
+    pub ca_file: Option<PathBuf>,
+    /// Added to our `User-Agent`
+    pub user_agent: Option<String>,
+}
+
+impl Default for HttpConfig {
+    /// Defines all the default values
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: CONNECT_TIMEOUT,
+            timeout: TIMEOUT,
+            proxy: None,
+            ca_file: None,
+            user_agent: None,
+        }
+    }
+}
+
+/// Default transport, using the blocking `reqwest` client
+///
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport {
+    agent: reqwest::blocking::Client,
+}
+
+impl Default for ReqwestTransport {
+    /// Defines all the default values
+    fn default() -> Self {
+        ReqwestTransport::with_config(&HttpConfig::default()).unwrap()
+    }
+}
+
+impl ReqwestTransport {
+    /// Use an already configured `reqwest` client
+    ///
+    pub fn new(agent: reqwest::blocking::Client) -> Self {
+        ReqwestTransport { agent }
+    }
+
+    /// Create the `reqwest` client from our settings
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use atlas_rs::transport::{HttpConfig, ReqwestTransport};
+    ///
+    /// let cfg = HttpConfig {
+    ///     timeout: Duration::from_secs(300),
+    ///     proxy: Some("socks5://localhost:1080".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let t = ReqwestTransport::with_config(&cfg).unwrap();
+    /// ```
+    ///
+    pub fn with_config(cfg: &HttpConfig) -> Result<Self, APIError> {
+        let ag = match &cfg.user_agent {
+            Some(sfx) => format!(
+                "{}/{} {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                sfx
+            ),
+            None => format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        };
+        let mut cb = reqwest::blocking::ClientBuilder::new()
+            .connect_timeout(cfg.connect_timeout)
+            .user_agent(&ag);
+
+        // `reqwest` has a default timeout we have to override
+        cb = if cfg.timeout.is_zero() {
+            cb.timeout(None)
+        } else {
+            cb.timeout(cfg.timeout)
+        };
+
+        if let Some(proxy) = &cfg.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|e| APIError::new(400, "Bad proxy", &e.to_string(), "with_config"))?;
+            cb = cb.proxy(proxy);
+        }
+
+        if let Some(fname) = &cfg.ca_file {
+            let pem = fs::read(fname)?;
+            let cert = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| APIError::new(400, "Bad CA file", &e.to_string(), "with_config"))?;
+            cb = cb.add_root_certificate(cert);
+        }
+
+        Ok(ReqwestTransport { agent: cb.build()? })
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+        let method = match req.method {
+            Method::Delete => reqwest::Method::DELETE,
+            Method::Get => reqwest::Method::GET,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+        };
+
+        // reqwest errors carry the full URL
+        let shown = redacted(&req.url);
+        let mut rb = self.agent.request(method, req.url);
+        for (k, v) in &req.headers {
+            rb = rb.header(k, v);
+        }
+        if let Some(body) = req.body {
+            rb = rb.body(body);
+        }
+
+        let resp = rb.send().map_err(|e| {
+            APIError::new(
+                e.status().map(|s| s.as_u16()).unwrap_or(500),
+                "Bad",
+                &format!("{} for url ({})", e.without_url(), shown),
+                "transport",
+            )
+        })?;
+
+        let headers = resp
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_string(),
+                    String::from_utf8_lossy(v.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+
+        Ok(HttpResponse {
+            status: resp.status().as_u16(),
+            headers,
+            body: Box::new(resp),
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::client::ClientBuilder;
+
+    use super::*;
+
+    /// Always give the same answer and remember the URLs
+    #[derive(Debug, Default)]
+    struct Canned {
+        urls: Mutex<Vec<String>>,
+    }
+
+    impl Transport for Canned {
+        fn send(&self, req: HttpRequest) -> Result<HttpResponse, APIError> {
+            self.urls.lock().unwrap().push(req.url.path().to_string());
+            let body =
+                r#"{"error":{"status":404,"code":104,"detail":"Not found.","title":"Not Found"}}"#;
+            Ok(HttpResponse::new(404, body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_custom_transport() {
+        let c = ClientBuilder::new()
+            .api_key("FOO")
+            .transport(Canned::default())
+            .build()
+            .unwrap();
+
+        let r = c.keys().delete("0123-abcd");
+        assert_eq!(404, r.unwrap_err().error.status);
+
+        let r = c.keys().list();
+        assert_eq!("Not found.", r.unwrap_err().error.detail);
+    }
+
+    #[test]
+    fn test_response() {
+        let r = HttpResponse::new(200, br#"{"a":1}"#.to_vec());
+
+        assert!(r.is_success());
+        let v: serde_json::Value = r.json().unwrap();
+        assert_eq!(1, v["a"]);
+    }
+
+    #[test]
+    fn test_response_header() {
+        let mut r = HttpResponse::new(404, vec![]);
+        r.headers.push(("etag".to_string(), "\"42\"".to_string()));
+
+        assert!(!r.is_success());
+        assert_eq!(Some("\"42\""), r.header("ETag"));
+        assert_eq!(None, r.header("last-modified"));
+    }
+
+    #[test]
+    fn test_with_config() {
+        let cfg = HttpConfig {
+            timeout: Duration::ZERO,
+            proxy: Some("socks5://localhost:1080".to_string()),
+            user_agent: Some("test/1.0".to_string()),
+            ..Default::default()
+        };
+
+        assert!(ReqwestTransport::with_config(&cfg).is_ok());
+    }
+
+    #[test]
+    fn test_with_config_bad_proxy() {
+        let cfg = HttpConfig {
+            proxy: Some("not a proxy".to_string()),
+            ..Default::default()
+        };
+
+        let e = ReqwestTransport::with_config(&cfg).unwrap_err();
+        assert_eq!("Bad proxy", e.error.title);
+    }
+
+    #[test]
+    fn test_with_config_no_ca_file() {
+        let cfg = HttpConfig {
+            ca_file: Some(PathBuf::from("/nonexistent.pem")),
+            ..Default::default()
+        };
+
+        assert!(ReqwestTransport::with_config(&cfg).is_err());
+    }
+
+    #[test]
+    fn test_send_error_redacted() {
+        let t = ReqwestTransport::with_config(&HttpConfig::default()).unwrap();
+        let url = Url::parse("http://127.0.0.1:1/api/v2/keys/SECRET/?key=OTHER").unwrap();
+
+        let e = t.send(HttpRequest::get(url)).unwrap_err();
+        assert!(e.error.detail.contains("/keys/REDACTED/"));
+        assert!(!e.error.detail.contains("SECRET"));
+        assert!(!e.error.detail.contains("OTHER"));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let url = Url::parse("https://example.net/api/v2/probes/?page=2").unwrap();
+        assert_eq!("/api/v2/probes/?page=2", redacted(&url));
+
+        let url = Url::parse("https://example.net/api/v2/keys/").unwrap();
+        assert_eq!("/api/v2/keys/", redacted(&url));
+
+        let url = Url::parse("https://example.net/api/v2/keys/SECRET/?key=OTHER").unwrap();
+        let s = redacted(&url);
+        assert!(!s.contains("SECRET"));
+        assert!(!s.contains("OTHER"));
+    }
+
+    #[test]
+    fn test_request_debug() {
+        let url = Url::parse("http://localhost/").unwrap();
+        let r = HttpRequest::get(url).header("Authorization", "Key SECRET");
+
+        assert!(!format!("{:?}", r).contains("SECRET"));
+
+        let url = Url::parse("https://example.net/api/v2/keys/UUID/?key=OTHER").unwrap();
+        let r = format!("{:?}", HttpRequest::get(url));
+        assert!(!r.contains("UUID"));
+        assert!(!r.contains("OTHER"));
+    }
+
+    #[test]
+    fn test_request_json() {
+        let url = Url::parse("http://localhost/").unwrap();
+        let r = HttpRequest::new(Method::Post, url).json(b"{}".to_vec());
+
+        assert_eq!(Some(b"{}".to_vec()), r.body);
+        assert_eq!("application/json", r.headers[0].1);
+        assert_eq!("POST", r.method.to_string());
+    }
+}
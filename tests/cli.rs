@@ -20,3 +20,34 @@ fn test_atlas_version() {
 
     cmd.arg("-V").assert().success();
 }
+
+#[test]
+fn test_atlas_probe_measurements_help() {
+    let mut cmd = Command::cargo_bin(BIN).unwrap();
+
+    cmd.args(["probe", "measurements", "-h"]).assert().success();
+}
+
+#[test]
+fn test_atlas_measurement_stop_help() {
+    let mut cmd = Command::cargo_bin(BIN).unwrap();
+
+    cmd.args(["measurement", "stop", "-h"]).assert().success();
+}
+
+#[test]
+fn test_atlas_cache_help() {
+    let mut cmd = Command::cargo_bin(BIN).unwrap();
+
+    cmd.args(["cache", "-h"]).assert().success();
+}
+
+#[test]
+fn test_atlas_parse() {
+    let mut cmd = Command::cargo_bin(BIN).unwrap();
+
+    cmd.args(["parse", "-n", "-p", "2", "tests/data/results.ndjson"])
+        .assert()
+        .success()
+        .stdout("1\n");
+}
@@ -0,0 +1,180 @@
+#![cfg(feature = "mock")]
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use atlas_rs::client::ClientBuilder;
+use atlas_rs::core::anchors::{Anchor, AnchorFilter};
+use atlas_rs::core::credits::Credits;
+use atlas_rs::core::keys::Key;
+use atlas_rs::core::measurements::{Measurement, WaitOpts};
+use atlas_rs::core::probes::Probe;
+use atlas_rs::core::results::MeasurementResult;
+use atlas_rs::ids::MeasurementId;
+use atlas_rs::kinds::MeasurementType;
+use atlas_rs::mock::MockServer;
+use atlas_rs::option::Options;
+
+#[test]
+fn test_mock_probes() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let p: Probe = c.probe().get(2).unwrap();
+    assert_eq!(Some("NL"), p.country_code.as_deref());
+
+    // More than one page
+    let l: Vec<Probe> = c.probe().list().unwrap();
+    assert_eq!(60, l.len());
+
+    let l: Vec<Probe> = c
+        .probe()
+        .with(Options::from([("country_code", "FR")]))
+        .list()
+        .unwrap();
+    assert_eq!(20, l.len());
+    assert!(l.iter().all(|p| p.country_code.as_deref() == Some("FR")));
+}
+
+#[test]
+fn test_mock_fields_sort() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let l: Vec<Probe> = c
+        .probe()
+        .fields(&["status"])
+        .sort("-id")
+        .with(Options::from([("country_code", "DE")]))
+        .list()
+        .unwrap();
+    assert_eq!(20, l.len());
+    assert!(l.windows(2).all(|w| w[0].id > w[1].id));
+    // Trimmed fields are not there at all
+    assert_eq!("Connected", l[0].status.as_ref().unwrap().name);
+    assert!(l[0].country_code.is_none());
+    assert!(l[0].tags.is_none());
+}
+
+#[test]
+fn test_mock_entity() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let p = c.get::<Probe>(2).unwrap();
+    assert_eq!(Some("NL"), p.country_code.as_deref());
+
+    // More than one page
+    let l = c.list::<Probe>(Options::new()).unwrap();
+    assert_eq!(60, l.len());
+
+    let l = c.list::<Anchor>(AnchorFilter::new().country("FR")).unwrap();
+    assert!(!l.is_empty());
+    assert!(l.iter().all(|a| a.country.as_deref() == Some("FR")));
+
+    let k = c.list::<Key>([("label", "nope")]).unwrap();
+    assert!(k.is_empty());
+
+    let e = c.get::<Measurement>(999).unwrap_err();
+    assert_eq!(404, e.error.status);
+}
+
+#[test]
+fn test_mock_errors() {
+    let srv = MockServer::start().unwrap();
+
+    let e = srv.client().probe().get(999).unwrap_err();
+    assert_eq!(404, e.error.status);
+    assert_eq!("Not found.", e.error.detail);
+
+    let c = ClientBuilder::new()
+        .api_key("WRONG")
+        .endpoint(srv.endpoint())
+        .build()
+        .unwrap();
+    let e = c.probe().get(1).unwrap_err();
+    assert_eq!(403, e.error.status);
+}
+
+#[test]
+fn test_mock_others() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let a: Vec<Anchor> = c.anchors().list().unwrap();
+    assert_eq!(3, a.len());
+
+    let k: Vec<Key> = c.keys().list().unwrap();
+    assert_eq!(2, k.len());
+
+    let cr: Credits = c.credits().info().unwrap();
+    assert_eq!(1000000, cr.current_balance);
+}
+
+#[test]
+fn test_mock_measurements() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let body = json!({
+        "definitions": [{"type": "traceroute", "af": 4, "target": "example.com"}],
+        "probes": [{"requested": 4, "type": "country", "value": "DE"}],
+        "is_oneoff": true,
+    });
+    let r: Value = c.measurement().create(&body).unwrap();
+    let id = r["measurements"][0].as_u64().unwrap() as u32;
+    assert_eq!(1002, id);
+
+    let m: Measurement = c.measurement().get(id).unwrap();
+    assert_eq!(Some(MeasurementType::Traceroute), m.mtype);
+    assert_eq!(Some(4), m.probes_scheduled);
+
+    let res: Vec<MeasurementResult> = c.measurement().results(id).unwrap();
+    assert_eq!(4, res.len());
+    assert!(res.iter().all(|r| r.mtype() == "traceroute"));
+
+    // Same thing, line by line
+    let n = c.measurement().results_reader(id).unwrap().count();
+    assert_eq!(4, n);
+}
+
+#[test]
+fn test_mock_wait_for() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    let opts = WaitOpts {
+        timeout: Duration::from_secs(5),
+        interval: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let done = c.wait_for(MeasurementId(1001), &opts).unwrap();
+    assert!(!done.timed_out);
+    assert_eq!(5, done.results.len());
+    assert_eq!(Some(vec![]), done.missing);
+
+    // Several polls, results already seen are not added again
+    let opts = WaitOpts {
+        fraction: 2.0,
+        timeout: Duration::from_millis(100),
+        interval: Duration::from_millis(10),
+    };
+    let done = c.wait_for(MeasurementId(1001), &opts).unwrap();
+    assert!(done.timed_out);
+    assert_eq!(5, done.results.len());
+}
+
+#[test]
+fn test_mock_stop() {
+    let srv = MockServer::start().unwrap();
+    let c = srv.client();
+
+    c.measurement().delete(1001).unwrap();
+
+    let m: Measurement = c.measurement().get(1001).unwrap();
+    assert_eq!("Stopped", m.status.unwrap().name);
+
+    let res: Vec<MeasurementResult> = c.measurement().latest(1001).unwrap();
+    assert_eq!(5, res.len());
+}